@@ -0,0 +1,278 @@
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::archive::list::{self, Listing, MAX_ENTRIES};
+
+/// Unpacked size may exceed the archive size by this factor before extraction is aborted.
+const MAX_RATIO: u64 = 200;
+/// Archives smaller than this are allowed to unpack to this much regardless of the ratio.
+const RATIO_FLOOR: u64 = 64 * 1024 * 1024;
+/// Hard cap on what a single archive may unpack to.
+const MAX_TOTAL: u64 = 16 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl Kind {
+    /// Supported archive kind from the file name, using the detected extension for plain `.zip`/`.tar`.
+    pub fn detect(path: &Path, ext: &str) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        let by_name = [
+            (".tar.gz", Kind::TarGz),
+            (".tgz", Kind::TarGz),
+            (".tar.xz", Kind::TarXz),
+            (".txz", Kind::TarXz),
+            (".tar.zst", Kind::TarZst),
+            (".tzst", Kind::TarZst),
+            (".tar", Kind::Tar),
+            (".zip", Kind::Zip),
+        ];
+        if let Some((_, kind)) = by_name.iter().find(|(suffix, _)| name.ends_with(suffix)) {
+            return Some(*kind);
+        }
+        match ext {
+            "zip" => Some(Kind::Zip),
+            "tar" => Some(Kind::Tar),
+            "gz" if list::is_tar_gz(path) => Some(Kind::TarGz),
+            _ => None,
+        }
+    }
+
+    fn tar_stream(self, file: File) -> Result<Box<dyn Read>> {
+        Ok(match self {
+            Kind::Tar => Box::new(file),
+            Kind::TarGz => Box::new(GzDecoder::new(file)),
+            Kind::TarXz => Box::new(xz2::read::XzDecoder::new(file)),
+            Kind::TarZst => Box::new(zstd::stream::read::Decoder::new(file)?),
+            Kind::Zip => unreachable!("zip is not a tar stream"),
+        })
+    }
+}
+
+/// Archive entry name as a path inside the staging directory; absolute paths and `..` are refused.
+/// Backslashes count as separators, as archivers on Windows write them.
+fn safe_path(name: &str) -> Result<PathBuf> {
+    let normalized = name.replace('\\', "/");
+    let path = Path::new(&normalized);
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => bail!("entry escapes the archive: {}", name),
+            Component::RootDir | Component::Prefix(_) => bail!("entry has an absolute path: {}", name),
+        }
+    }
+    if out.as_os_str().is_empty() {
+        bail!("entry has an empty path");
+    }
+    Ok(out)
+}
+
+/// Tracks unpacked bytes against the ratio and total limits.
+struct Budget {
+    remaining: u64,
+    entries: usize,
+}
+
+impl Budget {
+    fn new(archive: &Path) -> Result<Self> {
+        let size = fs::metadata(archive)?.len();
+        let allowed = size.saturating_mul(MAX_RATIO).clamp(RATIO_FLOOR, MAX_TOTAL);
+        Ok(Self { remaining: allowed, entries: 0 })
+    }
+
+    fn next_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            bail!("more than {} entries", MAX_ENTRIES);
+        }
+        Ok(())
+    }
+
+    /// Copies at most the remaining budget; running past it aborts the extraction.
+    fn copy(&mut self, reader: &mut dyn Read, target: &Path) -> Result<()> {
+        let mut out = File::create(target).with_context(|| format!("cannot create {}", target.display()))?;
+        let written = io::copy(&mut reader.take(self.remaining + 1), &mut out)?;
+        if written > self.remaining {
+            bail!("unpacked size exceeds the decompression limit");
+        }
+        self.remaining -= written;
+        Ok(())
+    }
+}
+
+fn write_entry(staging: &Path, rel: &Path, reader: &mut dyn Read, mode: Option<u32>, budget: &mut Budget) -> Result<PathBuf> {
+    let target = staging.join(rel);
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("cannot create dir {}", dir.display()))?;
+    }
+    budget.copy(reader, &target)?;
+    if mode.is_some_and(|m| m & 0o111 != 0) {
+        make_executable(&target)?;
+    }
+    Ok(target)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Files written to `staging`, plus the links that were left out.
+#[derive(Debug, Default)]
+pub struct Extracted {
+    pub files: Vec<PathBuf>,
+    pub skipped_links: Vec<String>,
+}
+
+/// Unpacks `archive` into `staging`. Any unsafe entry or tripped limit aborts the whole extraction;
+/// the caller is expected to discard `staging` in that case. Symlinks and hard links are never created.
+pub fn extract(archive: &Path, kind: Kind, staging: &Path) -> Result<Extracted> {
+    let mut budget = Budget::new(archive)?;
+    let mut out = Extracted::default();
+    fs::create_dir_all(staging).with_context(|| format!("cannot create dir {}", staging.display()))?;
+
+    if kind == Kind::Zip {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?).context("cannot read zip archive")?;
+        for i in 0..zip.len() {
+            budget.next_entry()?;
+            let mut entry = zip.by_index(i)?;
+            let rel = safe_path(entry.name())?;
+            let mode = entry.unix_mode();
+            if mode.is_some_and(|m| m & 0o170000 == 0o120000) {
+                out.skipped_links.push(entry.name().to_string());
+            } else if entry.is_dir() {
+                fs::create_dir_all(staging.join(&rel))?;
+            } else {
+                out.files.push(write_entry(staging, &rel, &mut entry, mode, &mut budget)?);
+            }
+        }
+        out.files.sort();
+        out.files.dedup();
+        return Ok(out);
+    }
+
+    let mut tar = tar::Archive::new(kind.tar_stream(File::open(archive)?)?);
+    for entry in tar.entries()? {
+        budget.next_entry()?;
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let rel = safe_path(&name)?;
+        let kind = entry.header().entry_type();
+        let mode = entry.header().mode().ok();
+        if kind.is_symlink() || kind.is_hard_link() {
+            out.skipped_links.push(name);
+        } else if kind.is_dir() {
+            fs::create_dir_all(staging.join(&rel))?;
+        } else if kind.is_file() {
+            out.files.push(write_entry(staging, &rel, &mut entry, mode, &mut budget)?);
+        }
+    }
+    out.files.sort();
+    out.files.dedup();
+    Ok(out)
+}
+
+/// Entry listing for a dry run, without writing anything. Applies the same path and size checks
+/// as `extract`, using the sizes the archive declares.
+pub fn preview(archive: &Path, kind: Kind) -> Result<Listing> {
+    let listing = match kind {
+        Kind::Zip => list::zip(archive)?,
+        Kind::Tar => list::tar(archive)?,
+        Kind::TarGz => list::tar_gz(archive)?,
+        Kind::TarXz | Kind::TarZst => list::tar_stream(kind.tar_stream(File::open(archive)?)?)?,
+    };
+    if listing.truncated {
+        bail!("more than {} entries or too large to list", MAX_ENTRIES);
+    }
+
+    let budget = Budget::new(archive)?;
+    let mut declared = 0u64;
+    for entry in &listing.entries {
+        safe_path(&entry.name)?;
+        declared = declared.saturating_add(entry.size);
+    }
+    if declared > budget.remaining {
+        bail!("unpacked size exceeds the decompression limit");
+    }
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-extract-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn zip_with(path: &Path, entries: &[&str]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for name in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.add_symlink("link", "/etc/passwd", SimpleFileOptions::default()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn entry_names_stay_inside_the_staging_dir() {
+        assert_eq!(safe_path("a/b.txt").unwrap(), Path::new("a/b.txt"));
+        assert_eq!(safe_path("./a//./b/").unwrap(), Path::new("a/b"));
+        assert_eq!(safe_path("dir\\file.txt").unwrap(), Path::new("dir/file.txt"));
+
+        for bad in ["../x", "a/../../x", "a/../b", "..\\x", "a\\..\\..\\x", "/etc/passwd", "\\abs", "", ".", "./"] {
+            assert!(safe_path(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn zips_unpack_without_links() {
+        let dir = scratch("zip");
+        let archive = dir.join("ok.zip");
+        zip_with(&archive, &["top.txt", "sub/inner.txt"]);
+
+        let staging = dir.join("staging");
+        let out = extract(&archive, Kind::Zip, &staging).unwrap();
+        assert_eq!(out.files, [staging.join("sub/inner.txt"), staging.join("top.txt")]);
+        assert_eq!(out.skipped_links, ["link"]);
+        assert!(fs::symlink_metadata(staging.join("link")).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn escaping_entries_abort_the_extraction() {
+        let dir = scratch("slip");
+        let archive = dir.join("slip.zip");
+        zip_with(&archive, &["fine.txt", "../escaped.txt"]);
+
+        let staging = dir.join("staging");
+        assert!(extract(&archive, Kind::Zip, &staging).is_err());
+        assert!(!dir.join("escaped.txt").exists());
+        assert!(preview(&archive, Kind::Zip).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Stop listing after this many entries; the rest of the archive is ignored.
+pub const MAX_ENTRIES: usize = 10_000;
+/// Largest zip central directory that will be read.
+const MAX_CENTRAL_DIR: u64 = 16 * 1024 * 1024;
+/// Decompressed bytes a compressed tar stream may produce while walking its headers.
+const MAX_STREAM_BYTES: u64 = 256 * 1024 * 1024;
+/// End of central directory record plus the longest possible comment.
+const EOCD_SEARCH: u64 = 22 + 65_535;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub size: u64,
+    /// Unix mode bits, when the archive records them
+    pub mode: Option<u32>,
+    pub is_dir: bool,
+    /// Symbolic or hard link
+    pub is_link: bool,
+}
+
+/// Entries of the archive, at most `MAX_ENTRIES`; `truncated` tells whether more were left unread.
+#[derive(Debug, Default)]
+pub struct Listing {
+    pub entries: Vec<Entry>,
+    pub truncated: bool,
+}
+
+fn le16(b: &[u8]) -> u64 {
+    u16::from_le_bytes([b[0], b[1]]) as u64
+}
+
+fn le32(b: &[u8]) -> u64 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64
+}
+
+/// Reads the zip central directory only; nothing is decompressed.
+pub fn zip(path: &Path) -> Result<Listing> {
+    let mut f = File::open(path)?;
+    let len = f.metadata()?.len();
+    let search = len.min(EOCD_SEARCH);
+    f.seek(SeekFrom::Start(len - search))?;
+    let mut tail = Vec::new();
+    (&mut f).take(search).read_to_end(&mut tail)?;
+
+    let eocd = tail
+        .windows(4)
+        .rposition(|w| w == b"PK\x05\x06")
+        .context("no end of central directory record")?;
+    let eocd = tail.get(eocd..eocd + 22).context("truncated end of central directory")?;
+    let (cd_size, cd_offset) = (le32(&eocd[12..16]), le32(&eocd[16..20]));
+    if cd_size == 0xFFFF_FFFF || cd_offset == 0xFFFF_FFFF {
+        bail!("zip64 archives are not inspected");
+    }
+
+    f.seek(SeekFrom::Start(cd_offset))?;
+    let mut cd = Vec::new();
+    (&mut f).take(cd_size.min(MAX_CENTRAL_DIR)).read_to_end(&mut cd)?;
+
+    let mut listing = Listing::default();
+    let mut pos = 0;
+    while let Some(header) = cd.get(pos..pos + 46) {
+        if &header[..4] != b"PK\x01\x02" {
+            break;
+        }
+        if listing.entries.len() == MAX_ENTRIES {
+            listing.truncated = true;
+            break;
+        }
+        let made_by_unix = header[5] == 3;
+        let size = le32(&header[24..28]);
+        let (name_len, extra_len, comment_len) =
+            (le16(&header[28..30]) as usize, le16(&header[30..32]) as usize, le16(&header[32..34]) as usize);
+        let attrs = le32(&header[38..42]) as u32;
+        let Some(name) = cd.get(pos + 46..pos + 46 + name_len) else { break };
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let mode = made_by_unix.then_some(attrs >> 16);
+        listing.entries.push(Entry {
+            is_dir: name.ends_with('/'),
+            is_link: mode.is_some_and(|m| m & 0o170000 == 0o120000),
+            mode,
+            name,
+            size,
+        });
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    listing.truncated |= cd_size > MAX_CENTRAL_DIR;
+    Ok(listing)
+}
+
+/// A read error part-way through (e.g. the gzip byte limit) ends the listing instead of failing it.
+fn collect_tar<R: Read>(entries: tar::Entries<'_, R>) -> Result<Listing> {
+    let mut listing = Listing::default();
+    for entry in entries {
+        if listing.entries.len() == MAX_ENTRIES {
+            listing.truncated = true;
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) if !listing.entries.is_empty() => {
+                listing.truncated = true;
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let header = entry.header();
+        let kind = header.entry_type();
+        listing.entries.push(Entry {
+            name: entry.path()?.to_string_lossy().into_owned(),
+            size: header.size().unwrap_or(0),
+            mode: header.mode().ok(),
+            is_dir: kind.is_dir(),
+            is_link: kind.is_symlink() || kind.is_hard_link(),
+        });
+    }
+    Ok(listing)
+}
+
+/// Walks tar headers, seeking over file data.
+pub fn tar(path: &Path) -> Result<Listing> {
+    let mut archive = tar::Archive::new(File::open(path)?);
+    collect_tar(archive.entries_with_seek()?)
+}
+
+/// Walks the tar headers inside a decompressed stream, which has to be read through, so it is capped.
+pub fn tar_stream<R: Read>(stream: R) -> Result<Listing> {
+    let mut archive = tar::Archive::new(stream.take(MAX_STREAM_BYTES));
+    collect_tar(archive.entries()?)
+}
+
+pub fn tar_gz(path: &Path) -> Result<Listing> {
+    tar_stream(GzDecoder::new(File::open(path)?))
+}
+
+/// Whether a gzip file holds a tar stream (`ustar` magic in the first header).
+pub fn is_tar_gz(path: &Path) -> bool {
+    let mut header = [0u8; 512];
+    File::open(path)
+        .map(GzDecoder::new)
+        .and_then(|mut gz| gz.read_exact(&mut header))
+        .is_ok_and(|_| &header[257..262] == b"ustar")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::tests::scratch;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::fs;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn zip_listing_reads_modes_dirs_and_links() {
+        let dir = scratch("list-zip");
+        let path = dir.join("a.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        writer.add_directory("bin/", options).unwrap();
+        writer.start_file("bin/run", options.unix_permissions(0o755)).unwrap();
+        writer.write_all(b"#!/bin/sh\n").unwrap();
+        writer.add_symlink("bin/latest", "run", options).unwrap();
+        writer.finish().unwrap();
+
+        let listing = zip(&path).unwrap();
+        let names: Vec<_> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["bin/", "bin/run", "bin/latest"]);
+        assert!(listing.entries[0].is_dir);
+        assert_eq!(listing.entries[1].mode.map(|m| m & 0o777), Some(0o755));
+        assert_eq!(listing.entries[1].size, 10);
+        assert!(listing.entries[2].is_link);
+        assert!(!listing.truncated);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cut_off_tar_gz_keeps_the_entries_read_so_far() {
+        let dir = scratch("list-cut");
+        let path = dir.join("a.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(File::create(&path).unwrap(), Compression::fast()));
+        for (name, size) in [("first.txt", 16), ("second.bin", 64 * 1024)] {
+            // A simple LCG keeps the data from compressing away
+            let data: Vec<u8> = (0..size as u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+            let mut header = tar::Header::new_ustar();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data.as_slice()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+        let whole = fs::read(&path).unwrap();
+        fs::write(&path, &whole[..whole.len() / 2]).unwrap();
+
+        let listing = tar_gz(&path).unwrap();
+        // The second header survives the cut, its data does not
+        let names: Vec<_> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["first.txt", "second.bin"]);
+        assert!(listing.truncated);
+
+        fs::write(&path, &whole[..10]).unwrap();
+        assert!(tar_gz(&path).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
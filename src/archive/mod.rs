@@ -0,0 +1,282 @@
+pub mod extract;
+pub mod list;
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::classify::Category;
+use crate::metadata::Metadata;
+use list::Listing;
+
+/// Share of files that must be images for an archive to count as a photo album.
+const ALBUM_SHARE: f64 = 0.8;
+/// Share of files that must be source code for an archive to count as a source tree.
+const SOURCES_SHARE: f64 = 0.5;
+const MAX_LISTED_EXECUTABLES: usize = 5;
+
+const PROJECT_MARKERS: &[&str] = &[
+    "cargo.toml", "package.json", "makefile", "cmakelists.txt", "setup.py", "pyproject.toml",
+    "go.mod", "pom.xml", "build.gradle", "meson.build", "configure",
+];
+
+fn is_junk(name: &str) -> bool {
+    let file = name.rsplit('/').next().unwrap_or(name);
+    name.starts_with("__MACOSX/") || matches!(file, ".DS_Store" | "Thumbs.db" | "desktop.ini")
+}
+
+/// Summary of what an archive holds, from its entry names and modes.
+#[derive(Debug, Default)]
+pub struct Contents {
+    pub files: usize,
+    /// Sum of the uncompressed sizes the archive declares
+    pub unpacked_bytes: u64,
+    pub pictures: usize,
+    pub code: usize,
+    pub has_project_marker: bool,
+    pub executables: Vec<String>,
+    pub truncated: bool,
+}
+
+impl Contents {
+    fn from_listing(listing: Listing) -> Self {
+        let mut contents = Contents {
+            truncated: listing.truncated,
+            ..Default::default()
+        };
+
+        for entry in listing.entries.iter().filter(|e| !e.is_dir && !e.is_link && !is_junk(&e.name)) {
+            contents.files += 1;
+            contents.unpacked_bytes = contents.unpacked_bytes.saturating_add(entry.size);
+            let file = entry.name.rsplit('/').next().unwrap_or(&entry.name).to_ascii_lowercase();
+            let ext = Path::new(&file)
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let category = Category::from_ext(&ext);
+            match category {
+                Category::Pictures => contents.pictures += 1,
+                Category::Code => contents.code += 1,
+                _ => {}
+            }
+            contents.has_project_marker |= PROJECT_MARKERS.contains(&file.as_str());
+
+            let exec_bit = entry.mode.is_some_and(|m| m & 0o111 != 0);
+            if matches!(category, Category::Executables) || exec_bit {
+                contents.executables.push(entry.name.clone());
+            }
+        }
+        contents
+    }
+
+    fn share(&self, count: usize) -> f64 {
+        match self.files {
+            0 => 0.0,
+            n => count as f64 / n as f64,
+        }
+    }
+
+    /// Where the archive should go instead of `Archives/`, e.g. `(Pictures, "Albums")`.
+    pub fn placement(&self) -> Option<(Category, &'static str)> {
+        if self.files >= 2 && self.share(self.pictures) >= ALBUM_SHARE {
+            Some((Category::Pictures, "Albums"))
+        } else if self.code > 0 && (self.has_project_marker || self.share(self.code) >= SOURCES_SHARE) {
+            Some((Category::Code, "Sources"))
+        } else {
+            None
+        }
+    }
+
+    /// Warning text when the archive carries executables, naming the first few.
+    pub fn executable_warning(&self, path: &Path) -> Option<String> {
+        if self.executables.is_empty() {
+            return None;
+        }
+        let mut names = self.executables[..self.executables.len().min(MAX_LISTED_EXECUTABLES)].join(", ");
+        if self.executables.len() > MAX_LISTED_EXECUTABLES {
+            names.push_str(&format!(" and {} more", self.executables.len() - MAX_LISTED_EXECUTABLES));
+        }
+        Some(format!("Archive contains executables: {} ({})", path.display(), names))
+    }
+
+    pub fn describe(&self, meta: &mut Metadata) {
+        let files = if self.truncated { format!("{}+", self.files) } else { self.files.to_string() };
+        meta.insert("archive_files".to_string(), files);
+        meta.insert("archive_unpacked_bytes".to_string(), self.unpacked_bytes.to_string());
+        if let Some((category, sub)) = self.placement() {
+            meta.insert("archive_contents".to_string(), format!("{}/{}", category.dir_name(), sub));
+        }
+        if !self.executables.is_empty() {
+            meta.insert("archive_executables".to_string(), self.executables.len().to_string());
+        }
+    }
+}
+
+/// Lists a zip, tar or gzipped tar archive; `None` for formats that are not inspected.
+pub fn inspect(path: &Path, ext: &str) -> Result<Option<Contents>> {
+    let listing = match ext {
+        "zip" => list::zip(path)?,
+        "tar" => list::tar(path)?,
+        "gz" | "tgz" if list::is_tar_gz(path) => list::tar_gz(path)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(Contents::from_listing(listing)))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::SimpleFileOptions;
+
+    pub fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-archive-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a zip holding `(name, unix mode)` entries, each containing its own name.
+    pub fn zip_with(path: &Path, entries: &[(&str, u32)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, mode) in entries {
+            zip.start_file(*name, SimpleFileOptions::default().unix_permissions(*mode)).unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Writes a gzipped tar holding `(name, mode)` entries, each containing its own name.
+    pub fn tar_gz_with(path: &Path, entries: &[(&str, u32)]) {
+        let gz = GzEncoder::new(File::create(path).unwrap(), Compression::fast());
+        let mut tar = tar::Builder::new(gz);
+        for (name, mode) in entries {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(name.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            tar.append_data(&mut header, name, name.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn placed(contents: &Contents) -> Option<String> {
+        contents.placement().map(|(category, sub)| format!("{}/{}", category.dir_name(), sub))
+    }
+
+    fn placement(path: &Path, ext: &str) -> Option<String> {
+        placed(&inspect(path, ext).unwrap().unwrap())
+    }
+
+    #[test]
+    fn photo_zips_go_to_albums() {
+        let dir = scratch("album");
+        let album = dir.join("holiday.zip");
+        zip_with(
+            &album,
+            &[("a.jpg", 0o644), ("b.JPG", 0o644), ("c.png", 0o644), ("d.heic", 0o644), ("notes.txt", 0o644)],
+        );
+        assert_eq!(placement(&album, "zip"), Some("Pictures/Albums".to_string()));
+
+        // 3 of 5 is below the album share
+        let mixed = dir.join("mixed.zip");
+        zip_with(&mixed, &[("a.jpg", 0o644), ("b.jpg", 0o644), ("c.jpg", 0o644), ("x.txt", 0o644), ("y.pdf", 0o644)]);
+        assert_eq!(placement(&mixed, "zip"), None);
+
+        // A lone picture is not an album, and macOS junk does not count
+        let single = dir.join("single.zip");
+        zip_with(&single, &[("a.jpg", 0o644), ("__MACOSX/._a.jpg", 0o644), (".DS_Store", 0o644)]);
+        let contents = inspect(&single, "zip").unwrap().unwrap();
+        assert_eq!(contents.files, 1);
+        assert_eq!(placed(&contents), None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn source_tarballs_go_to_sources() {
+        let dir = scratch("sources");
+        let project = dir.join("tool-1.0.tar.gz");
+        tar_gz_with(
+            &project,
+            &[
+                ("tool/Cargo.toml", 0o644),
+                ("tool/README.md", 0o644),
+                ("tool/LICENSE", 0o644),
+                ("tool/src/main.rs", 0o644),
+                ("tool/build.sh", 0o755),
+            ],
+        );
+        let contents = inspect(&project, "gz").unwrap().unwrap();
+        assert!(contents.has_project_marker);
+        assert_eq!(placed(&contents).as_deref(), Some("Code/Sources"));
+        assert_eq!(contents.executables, ["tool/build.sh"]);
+        assert!(contents.executable_warning(&project).unwrap().contains("tool/build.sh"));
+
+        // Without a marker, code has to make up half the files
+        let loose = dir.join("loose.tgz");
+        tar_gz_with(&loose, &[("a.py", 0o644), ("b.txt", 0o644), ("c.txt", 0o644)]);
+        assert_eq!(placement(&loose, "tgz"), None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn other_formats_and_plain_gzip_are_not_inspected() {
+        let dir = scratch("formats");
+        let gz = dir.join("notes.txt.gz");
+        let mut enc = GzEncoder::new(File::create(&gz).unwrap(), Compression::fast());
+        enc.write_all(b"just text, not a tar stream").unwrap();
+        enc.finish().unwrap();
+        assert!(inspect(&gz, "gz").unwrap().is_none());
+        assert!(inspect(&gz, "7z").unwrap().is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn corrupt_and_empty_archives_do_not_panic() {
+        let dir = scratch("corrupt");
+        let empty_zip = dir.join("empty.zip");
+        zip_with(&empty_zip, &[]);
+        let contents = inspect(&empty_zip, "zip").unwrap().unwrap();
+        assert_eq!((contents.files, placed(&contents)), (0, None));
+        assert!(contents.executable_warning(&empty_zip).is_none());
+
+        let garbage = dir.join("garbage.zip");
+        fs::write(&garbage, b"PK\x03\x04 this is not really a zip").unwrap();
+        assert!(inspect(&garbage, "zip").is_err());
+
+        let zero = dir.join("zero.zip");
+        fs::write(&zero, b"").unwrap();
+        assert!(inspect(&zero, "zip").is_err());
+
+        // A central directory pointing past the end of the file lists nothing
+        let mut lying = vec![0u8; 22];
+        lying[..4].copy_from_slice(b"PK\x05\x06");
+        lying[12..16].copy_from_slice(&1000u32.to_le_bytes());
+        lying[16..20].copy_from_slice(&5000u32.to_le_bytes());
+        let lying_zip = dir.join("lying.zip");
+        fs::write(&lying_zip, &lying).unwrap();
+        assert_eq!(inspect(&lying_zip, "zip").unwrap().unwrap().files, 0);
+
+        let empty_tar = dir.join("empty.tar");
+        fs::write(&empty_tar, [0u8; 1024]).unwrap();
+        assert_eq!(inspect(&empty_tar, "tar").unwrap().unwrap().files, 0);
+
+        let bad_tar = dir.join("bad.tar");
+        fs::write(&bad_tar, [b'x'; 512]).unwrap();
+        assert!(inspect(&bad_tar, "tar").is_err());
+
+        let empty_gz = dir.join("empty.tar.gz");
+        tar_gz_with(&empty_gz, &[]);
+        assert!(inspect(&empty_gz, "gz").unwrap().is_none());
+
+        let corrupt_gz = dir.join("corrupt.tgz");
+        fs::write(&corrupt_gz, b"\x1f\x8b\x08\x00garbage").unwrap();
+        assert!(inspect(&corrupt_gz, "tgz").unwrap().is_none());
+        assert!(list::tar_gz(&corrupt_gz).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
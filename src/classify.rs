@@ -9,9 +9,23 @@ pub enum Category {
     Code,
     Uncategorized,
     Mismatch,
+    Quarantine,
 }
 
 impl Category {
+    pub const ALL: [Category; 10] = [
+        Category::Video,
+        Category::Audio,
+        Category::Pictures,
+        Category::Documents,
+        Category::Archives,
+        Category::Executables,
+        Category::Code,
+        Category::Uncategorized,
+        Category::Mismatch,
+        Category::Quarantine,
+    ];
+
     pub fn dir_name(&self) -> &'static str {
         match self {
             Category::Video => "Video",
@@ -23,9 +37,17 @@ impl Category {
             Category::Code => "Code",
             Category::Uncategorized => "Uncategorized",
             Category::Mismatch => "Check manually",
+            Category::Quarantine => "Quarantine",
         }
     }
 
+    pub fn is_raw_photo(ext: &str) -> bool {
+        matches!(
+            ext.to_ascii_lowercase().as_str(),
+            "raw" | "cr2" | "cr3" | "nef" | "arw" | "dng" | "raf" | "orf" | "rw2"
+        )
+    }
+
     pub fn from_ext(ext: &str) -> Self {
         let ext = ext.to_ascii_lowercase();
         match ext.as_str() {
@@ -39,7 +61,7 @@ impl Category {
 
             "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "tiff" | "tif"
             | "svg" | "ico" | "heic" | "heif" | "raw" | "cr2" | "nef" 
-            | "arw" | "dng" | "psd" | "ai" | "eps" => Category::Pictures,
+            | "arw" | "dng" | "cr3" | "raf" | "orf" | "rw2" | "psd" | "ai" | "eps" => Category::Pictures,
 
             "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx"
             | "txt" | "md" | "rtf" | "odt" | "ods" | "odp" 
@@ -49,7 +71,7 @@ impl Category {
             | "xz" | "zst" | "lz4" | "cab" | "iso" | "dmg" => Category::Archives,
 
             "exe" | "msi" | "elf" | "app" | "mach-o" | "wasm"
-            | "dll" | "so" | "dylib" | "bin" => Category::Executables,
+            | "dll" | "so" | "dylib" | "bin" | "appimage" | "deb" | "rpm" => Category::Executables,
 
             "rs" | "py" | "js" | "jsx" | "tsx" | "c" | "cpp" | "h" | "hpp"
             | "java" | "go" | "rb" | "php" | "swift" | "kt" | "cs" | "html" | "css"
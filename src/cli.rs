@@ -1,8 +1,28 @@
-use clap::Parser;
+use clap::{ArgAction, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-#[derive(Parser, Debug)]
-#[command(author, version)]
+use crate::filter::{parse_date, parse_duration, parse_size};
+use crate::layout::{DEFAULT_AUDIO_LAYOUT, DEFAULT_EXEC_LAYOUT, DEFAULT_PHOTO_LAYOUT, DEFAULT_VIDEO_LAYOUT};
+use crate::ops::{SymlinkPolicy, TransferMode};
+use crate::template::Template;
+use crate::updater::Channel;
+
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  every file was sorted (or, with --dry-run, would be)
+  1  error; nothing or only part of the directory was sorted
+  2  invalid command line
+  3  nothing to do: no files found, or all of them filtered out
+  4  finished, but some files were skipped or left in place
+  5  finished, but some files could not be sorted (see the Failed section)";
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, after_help = EXIT_STATUS_HELP)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Disable signature detection and sort files by extension only (like the legacy FileSorter).
     #[arg(long)]
     pub ext_only: bool,
@@ -11,11 +31,243 @@ pub struct Args {
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Lay out audio files by their tags inside Audio/ (default: "{artist}/{album}/[{track:02} - ]{title}.{ext}")
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_AUDIO_LAYOUT,
+        value_parser = Template::parse
+    )]
+    pub audio_layout: Option<Template>,
+
+    /// Lay out camera photos by EXIF inside Pictures/ (default: "{year}/{year}-{month}-{day}");
+    /// screenshots and edited images go to Pictures/Screenshots and Pictures/Edited
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_PHOTO_LAYOUT,
+        value_parser = Template::parse
+    )]
+    pub photo_layout: Option<Template>,
+
+    /// Lay out videos by container metadata inside Video/ (default: "{year}/{year}-{month}-{day}");
+    /// fields include {resolution_class}, {length_class}, {duration_secs} and {resolution}
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_VIDEO_LAYOUT,
+        value_parser = Template::parse
+    )]
+    pub video_layout: Option<Template>,
+
+    /// Lay out executables by platform inside Executables/ (default: "{platform}[-{arch}][/{exec_group}]");
+    /// fields include {exec_format}, {exec_kind}, {bits} and {interpreter}
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_EXEC_LAYOUT,
+        value_parser = Template::parse
+    )]
+    pub exec_layout: Option<Template>,
+
+    /// Videos shorter than this count as clips ({length_class} = "Clips")
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "60s")]
+    pub clip_max: Duration,
+
+    /// Sort subtitle, .xmp, .cue and other companion files on their own instead of
+    /// keeping them with their primary file
+    #[arg(long)]
+    pub no_sidecars: bool,
+
+    /// Look inside zip and tar archives: photo sets go to Pictures/Albums, source trees to
+    /// Code/Sources, and archives carrying executables are flagged
+    #[arg(long)]
+    pub inspect_archives: bool,
+
+    /// Unpack zip, tar, tar.gz, tar.xz and tar.zst archives and sort their contents;
+    /// the archive itself goes to Archives/Extracted
+    #[arg(long)]
+    pub extract: bool,
+
+    /// With --extract, delete archives after their contents were sorted
+    #[arg(long, requires = "extract")]
+    pub delete_extracted: bool,
+
+    /// Move risky files (disguised executables, double extensions, macro documents,
+    /// right-to-left names, scripted HTML/SVG) to Quarantine/ with their permissions stripped
+    #[arg(long)]
+    pub quarantine: bool,
+
+    /// Learn which files live where in an existing folder tree (e.g. Work/Contracts, Personal/Taxes)
+    /// and propose those folders for new files; each proposal is confirmed unless --confidence is given
+    #[arg(long, value_name = "DIR", global = true)]
+    pub learn_from: Option<PathBuf>,
+
+    /// With --learn-from, apply proposals at least this confident (50-100, in percent) without
+    /// asking and sort the rest as usual
+    #[arg(long, value_name = "PERCENT", requires = "learn_from", global = true,
+          value_parser = clap::value_parser!(u8).range(50..=100))]
+    pub confidence: Option<u8>,
+
+    /// How files get to their destination; every mode but move leaves the original folder as it is
+    #[arg(long, value_enum, value_name = "MODE", default_value = "move")]
+    pub mode: TransferMode,
+
+    /// What to do with symbolic links: leave them (skip), move the link itself (move-link),
+    /// or sort the file it points to, which stays where it is (follow-target)
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "skip")]
+    pub symlinks: SymlinkPolicy,
+
+    /// Record each sorted file's category and original path in its
+    /// user.sortify.category / user.sortify.source extended attributes (shown by `identify`)
+    #[arg(long)]
+    pub tag: bool,
+
+    /// Stop at the first file that cannot be sorted instead of recording it as failed
+    /// and carrying on
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Print nothing but failures (the report is still printed with --json)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more: -v adds file metadata and collision renames, -vv how each file was detected
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Do not record this run in the history (<state dir>/sortify/history)
+    #[arg(long)]
+    pub no_history: bool,
+
+    /// Print the report as JSON instead of the colored summary
+    #[arg(long, global = true)]
+    pub json: bool,
+
     /// Skip checking for updates on startup
     #[arg(long)]
     pub no_check_updates: bool,
 
-    /// Enable the pre-release update channel
-    #[arg(long)]
+    /// Enable the pre-release update channel (same as --channel beta)
+    #[arg(long, conflicts_with = "channel")]
     pub prerelease_channel: bool,
-}
\ No newline at end of file
+
+    /// Release channel to check for updates
+    #[arg(long, value_enum, value_name = "CHANNEL")]
+    pub channel: Option<Channel>,
+
+    /// Download, verify and install the latest version, then exit
+    #[arg(long)]
+    pub update: bool,
+
+    /// Release API base URL, or a local release JSON file, to check for updates
+    #[arg(long, value_name = "URL|PATH")]
+    pub update_source: Option<String>,
+
+    /// Minimum time between update checks (e.g. 12h, 1d; 0 checks every run)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub update_interval: Option<Duration>,
+
+    /// Path to the config file (default: <config dir>/sortify/config.toml)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Path to a signature database (default: <config dir>/sortify/signatures.toml)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub signatures: Option<PathBuf>,
+
+    /// Only sort files not modified within this duration (e.g. 30m, 2h, 7d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub min_age: Option<Duration>,
+
+    /// Only sort files modified within this duration (e.g. 30m, 2h, 7d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_age: Option<Duration>,
+
+    /// Only sort files modified after this date (YYYY-MM-DD[THH:MM[:SS]], UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub newer_than: Option<SystemTime>,
+
+    /// Only sort files modified before this date (YYYY-MM-DD[THH:MM[:SS]], UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub older_than: Option<SystemTime>,
+
+    /// Only sort files at least this large (e.g. 512K, 10M, 2G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only sort files at most this large (e.g. 512K, 10M, 2G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Inspect the file signature database
+    Signatures {
+        #[command(subcommand)]
+        action: SignaturesAction,
+    },
+    /// Show how files would be detected, classified and placed, without moving them
+    Identify {
+        /// Files to identify; directories stand for the files directly inside them
+        #[arg(required = true, value_name = "PATH")]
+        paths: Vec<PathBuf>,
+    },
+    /// Report what a directory tree contains, by category and extension, without moving anything
+    Stats {
+        /// Directory to analyse (default: the current directory)
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+        /// How many extensions, largest and oldest files to list
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+    },
+    /// List, show, export and undo past runs
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HistoryAction {
+    /// List recorded runs, oldest first (the default)
+    List,
+    /// Show everything recorded about one run
+    Show {
+        /// Run id, or "last"
+        id: String,
+    },
+    /// Write run records as JSON, for audits or to undo a run on another machine
+    Export {
+        /// Run ids (default: all runs)
+        ids: Vec<String>,
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Move the files a run sorted back where they were
+    Undo {
+        /// Run id, or "last"
+        id: String,
+        /// Take the run from an exported file instead of the local history
+        #[arg(long, value_name = "PATH")]
+        from: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SignaturesAction {
+    /// List all signatures, built-in and from the signature file
+    List,
+    /// Show which signatures match a file and which one decides its type
+    Test {
+        /// File to test
+        file: PathBuf,
+    },
+}
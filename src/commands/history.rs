@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::HistoryAction;
+use crate::history::{RunRecord, format_time};
+use crate::{print_results, print_summary};
+
+pub fn run(action: Option<&HistoryAction>, json: bool) -> Result<()> {
+    match action {
+        None | Some(HistoryAction::List) => list(json),
+        Some(HistoryAction::Show { id }) => show(id, json),
+        Some(HistoryAction::Export { ids, output }) => export(ids, output.as_deref()),
+        Some(HistoryAction::Undo { id, from }) => undo(id, from.as_deref()),
+    }
+}
+
+fn status(record: &RunRecord) -> ColoredString {
+    if record.undone.is_some() {
+        "undone".bright_black()
+    } else if !record.errors.is_empty() {
+        "failed".red()
+    } else if !record.result.failed.is_empty() {
+        "partial".yellow()
+    } else if record.dry_run {
+        "dry run".cyan()
+    } else {
+        "done".green()
+    }
+}
+
+fn list(json: bool) -> Result<()> {
+    let records = RunRecord::all()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("{}", "No runs recorded yet.".dimmed());
+        return Ok(());
+    }
+
+    println!(
+        "  {}",
+        format!("{:<18} {:<19} {:>6} {:>7} {:>5}  {:<8} {}", "id", "started (UTC)", "moved", "skipped", "warn", "status", "directory").dimmed()
+    );
+    for record in &records {
+        println!(
+            "  {:<18} {:<19} {:>6} {:>7} {:>5}  {:<8} {}",
+            record.id.bold(),
+            format_time(record.started),
+            record.result.moved.len(),
+            record.result.skipped.len(),
+            record.result.warnings.len(),
+            status(record),
+            record.root.display()
+        );
+    }
+    Ok(())
+}
+
+fn show(id: &str, json: bool) -> Result<()> {
+    let record = RunRecord::load(id)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(());
+    }
+
+    println!("{} {} ({})", "Run".bold(), record.id.bold(), status(&record));
+    println!("  {:<10} {}", "Started:", format_time(record.started));
+    println!("  {:<10} {}", "Finished:", format_time(record.finished));
+    println!("  {:<10} {}", "Directory:", record.root.display());
+    println!("  {:<10} {}", "Command:", record.command_line.join(" "));
+    if let Some(at) = record.undone {
+        println!("  {:<10} {}", "Undone:", format_time(at));
+    }
+    println!();
+
+    print_results(&record.result, record.dry_run);
+    if !record.errors.is_empty() {
+        println!("\n{}", "Errors:".red().bold());
+        for err in &record.errors {
+            println!("  {}", err.red());
+        }
+    }
+    print_summary(&record.result, record.dry_run);
+    Ok(())
+}
+
+fn export(ids: &[String], output: Option<&Path>) -> Result<()> {
+    let records = match ids.is_empty() {
+        true => RunRecord::all()?,
+        false => ids.iter().map(|id| RunRecord::load(id)).collect::<Result<Vec<_>>>()?,
+    };
+    let data = serde_json::to_string_pretty(&records)?;
+    match output {
+        Some(path) => {
+            fs::write(path, data).with_context(|| format!("cannot write {}", path.display()))?;
+            println!("Exported {} run(s) to {}", records.len(), path.display());
+        }
+        None => println!("{}", data),
+    }
+    Ok(())
+}
+
+/// Picks run `id` out of an export; `last` is its most recent run.
+fn from_export(path: &Path, id: &str) -> Result<RunRecord> {
+    let data = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let mut records: Vec<RunRecord> =
+        serde_json::from_slice(&data).with_context(|| format!("cannot parse {}", path.display()))?;
+    records.sort_by_key(|r| r.started);
+    let found = match id {
+        "last" => records.pop(),
+        _ => records.into_iter().find(|r| r.id == id),
+    };
+    found.with_context(|| format!("no run with id '{}' in {}", id, path.display()))
+}
+
+fn undo(id: &str, from: Option<&Path>) -> Result<()> {
+    let mut record = match from {
+        Some(path) => from_export(path, id)?,
+        None => RunRecord::load(id)?,
+    };
+    let report = record.undo()?;
+
+    println!("{} {}", "Undid run".green().bold(), record.id.bold());
+    for line in &report.restored {
+        println!("  {}", line.dimmed());
+    }
+    for path in &report.removed {
+        println!("  {} {}", "removed".dimmed(), path.dimmed());
+    }
+    if !report.failed.is_empty() {
+        println!("\n{}", "Could not undo:".yellow().bold());
+        for line in &report.failed {
+            println!("  {}", line);
+        }
+    }
+
+    println!("\nSummary:");
+    println!("  {} {}", "Restored:".green(), report.restored.len().to_string().bold());
+    if !report.removed.is_empty() {
+        println!("  {} {}", "Removed:".green(), report.removed.len().to_string().bold());
+    }
+    if !report.failed.is_empty() {
+        println!("  {} {}", "Not undone:".yellow(), report.failed.len().to_string().bold());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProcessingResult;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn record(started: u64, dry_run: bool) -> RunRecord {
+        let mut record = RunRecord::new(UNIX_EPOCH + Duration::from_secs(started), Path::new("/tmp"), dry_run, ProcessingResult::new(), None);
+        record.id = started.to_string();
+        record
+    }
+
+    #[test]
+    fn status_reflects_how_a_run_ended() {
+        assert_eq!(status(&record(1, false)).to_string(), "done".green().to_string());
+        assert_eq!(status(&record(1, true)).to_string(), "dry run".cyan().to_string());
+        let mut failed = record(1, false);
+        failed.errors.push("boom".to_string());
+        assert_eq!(status(&failed).to_string(), "failed".red().to_string());
+        failed.undone = Some(2);
+        assert_eq!(status(&failed).to_string(), "undone".bright_black().to_string());
+    }
+
+    #[test]
+    fn exports_are_searched_by_id_or_last() {
+        let path = std::env::temp_dir().join(format!("sortify-history-export-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_vec(&[record(300, false), record(100, false), record(200, true)]).unwrap()).unwrap();
+        assert_eq!(from_export(&path, "last").unwrap().id, "300");
+        assert!(from_export(&path, "200").unwrap().dry_run);
+        assert!(from_export(&path, "999").is_err());
+        fs::remove_file(&path).ok();
+    }
+}
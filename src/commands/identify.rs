@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::classify::Category;
+use crate::cli::Args;
+use crate::detect::{detect, ext_from_path, read_header, Detection, Detector};
+use crate::layout::Layouts;
+use crate::learn::Proposal;
+use crate::metadata::exec;
+use crate::ops::{self, Provenance};
+use crate::safety;
+use crate::signatures;
+
+#[derive(Debug, Serialize)]
+struct SignatureMatch {
+    name: String,
+    ext: String,
+    source: String,
+}
+
+/// What a sort run would do with one file.
+#[derive(Debug, Default, Serialize)]
+struct Identification {
+    path: String,
+    declared_ext: Option<String>,
+    signature: Option<SignatureMatch>,
+    detector: &'static str,
+    /// Extension the file is classified by
+    ext: String,
+    /// Signature and extension disagree; a real run asks which one to use
+    mismatch: bool,
+    /// A binary signature fired; a real run asks before moving the file
+    binary: bool,
+    executable: Option<String>,
+    /// Reasons the file would be quarantined
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    risks: Vec<String>,
+    category: &'static str,
+    /// Where the file would go, relative to the directory it is in
+    target: String,
+    /// `--learn-from`: the folder proposed for the file; `target` when it applies without asking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    learned: Option<Proposal>,
+    /// Where an earlier `--tag` run found the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sorted_from: Option<Provenance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Files named on the command line; a directory contributes the files directly inside it.
+fn expand(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut inner: Vec<PathBuf> = fs::read_dir(path)
+                .with_context(|| format!("cannot read dir {}", path.display()))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect();
+            inner.sort();
+            files.extend(inner);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn identify(path: &Path, args: &Args, layouts: &mut Layouts) -> Result<Identification> {
+    let declared = ext_from_path(path);
+    let mut id = Identification {
+        path: path.display().to_string(),
+        declared_ext: declared.clone(),
+        ..Default::default()
+    };
+
+    let buf = read_header(path)?;
+    let detection = match args.ext_only {
+        true => Detection { ext: None, signature: None, detector: Detector::Extension },
+        false => detect(&buf),
+    };
+    id.detector = detection.detector.name();
+    id.signature = detection.signature.map(|sig| SignatureMatch {
+        name: sig.name.clone(),
+        ext: sig.ext.clone(),
+        source: sig.source.to_string(),
+    });
+    id.mismatch = matches!((detection.ext, declared.as_deref()), (Some(sig), Some(real)) if sig != real);
+    id.ext = match detection.ext {
+        Some(ext) => ext.to_string(),
+        None => declared.unwrap_or_else(|| "unknown".to_string()),
+    };
+    id.binary = !args.ext_only && signatures::db().is_binary(&buf);
+    let info = exec::inspect(path);
+    id.executable = info.as_ref().map(|i| i.to_string());
+
+    if args.quarantine {
+        id.risks = safety::assess(path);
+    }
+    let file_name = path.file_name().unwrap_or_default();
+    let dest = if !id.risks.is_empty() {
+        id.category = Category::Quarantine.dir_name();
+        Path::new(Category::Quarantine.dir_name()).join(file_name)
+    } else {
+        let mut category = Category::from_ext(&id.ext);
+        if !args.ext_only && matches!(category, Category::Uncategorized) && info.is_some() {
+            category = Category::Executables;
+        }
+        let contents = match category {
+            Category::Archives if args.inspect_archives => archive::inspect(path, &id.ext)?,
+            _ => None,
+        };
+        let dest = match contents.as_ref().and_then(|c| c.placement()) {
+            Some((folder, sub)) => {
+                category = folder;
+                Path::new(folder.dir_name()).join(sub).join(file_name)
+            }
+            None => {
+                let usual = layouts.destination(path, &id.ext, &category)?.0;
+                id.learned = layouts.learned.as_ref().and_then(|l| l.model.propose(path, &id.ext, &category));
+                match (&id.learned, &layouts.learned) {
+                    (Some(proposal), Some(learned)) if learned.applies(proposal) => {
+                        proposal.folder.join(file_name)
+                    }
+                    _ => usual,
+                }
+            }
+        };
+        id.category = category.dir_name();
+        dest
+    };
+    id.target = path.parent().unwrap_or(Path::new("")).join(dest).display().to_string();
+    id.sorted_from = ops::provenance(path);
+    Ok(id)
+}
+
+fn print_identification(id: &Identification) {
+    let name = safety::escape_bidi(&id.path);
+    if let Some(err) = &id.error {
+        println!("{}: {}", name.bold(), err.red());
+        return;
+    }
+
+    let kind = match (&id.signature, id.detector) {
+        (Some(sig), _) => sig.name.clone(),
+        (None, "json heuristic") => "JSON data".to_string(),
+        (None, _) if id.declared_ext.is_none() => "unknown type".to_string(),
+        (None, _) => format!(".{} by extension", id.ext),
+    };
+    println!("{}: {} {} {}", name.bold(), kind, "→".bright_black(), id.target.green());
+
+    let declared = id.declared_ext.as_deref().map(|e| format!(".{}", e)).unwrap_or_else(|| "none".to_string());
+    println!("  {:<11} {}", "extension", declared);
+    match &id.signature {
+        Some(sig) => println!("  {:<11} {} {}", "signature", sig.name, format!("(.{}, {})", sig.ext, sig.source).dimmed()),
+        None => println!("  {:<11} {}", "signature", "none".dimmed()),
+    }
+    println!("  {:<11} {}", "detector", id.detector);
+    if id.mismatch {
+        println!(
+            "  {:<11} {}",
+            "mismatch",
+            format!("content says .{}, name says {}; a real run asks", id.ext, declared).yellow()
+        );
+    }
+    match id.binary {
+        true => println!("  {:<11} {}", "binary", "yes (a real run asks before moving it)".yellow()),
+        false => println!("  {:<11} no", "binary"),
+    }
+    if let Some(exec) = &id.executable {
+        println!("  {:<11} {}", "executable", exec);
+    }
+    for risk in &id.risks {
+        println!("  {:<11} {}", "risk", risk.red());
+    }
+    println!("  {:<11} {}", "category", id.category.cyan());
+    println!("  {:<11} {}", "target", id.target);
+    if let Some(proposal) = &id.learned {
+        println!(
+            "  {:<11} {} {}",
+            "learned",
+            proposal.folder.display(),
+            format!("({}% confident)", proposal.confidence).dimmed()
+        );
+    }
+    if let Some(tag) = &id.sorted_from {
+        println!(
+            "  {:<11} {} {}",
+            "sorted from",
+            safety::escape_bidi(&tag.source.display().to_string()),
+            format!("(into {})", tag.category).dimmed()
+        );
+    }
+}
+
+pub fn run(paths: &[PathBuf], args: &Args) -> Result<()> {
+    let cwd = std::env::current_dir().context("cannot get current directory")?;
+    let mut layouts = Layouts::from_args(args).learn(args, &cwd)?;
+    let files = expand(paths)?;
+
+    let ids: Vec<Identification> = files
+        .iter()
+        .map(|path| {
+            identify(path, args, &mut layouts).unwrap_or_else(|err| Identification {
+                path: path.display().to_string(),
+                error: Some(format!("{:#}", err)),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&ids)?);
+        return Ok(());
+    }
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_identification(id);
+    }
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod history;
+pub mod identify;
+pub mod signatures;
+pub mod stats;
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+use crate::cli::SignaturesAction;
+use crate::detect::read_header;
+use crate::signatures::{Signature, SignatureDb};
+
+pub fn run(action: &SignaturesAction, db: &SignatureDb) -> Result<()> {
+    match action {
+        SignaturesAction::List => list(db),
+        SignaturesAction::Test { file } => test(db, file)?,
+    }
+    Ok(())
+}
+
+fn print_entry(sig: &Signature) {
+    let binary = if sig.binary { " binary".yellow().to_string() } else { String::new() };
+    println!(
+        "  {:>4}  {:<8} {}{}  {}",
+        sig.priority,
+        format!(".{}", sig.ext).cyan(),
+        sig.name.bold(),
+        binary,
+        format!("({})", sig.source).dimmed()
+    );
+}
+
+fn list(db: &SignatureDb) {
+    let mut entries: Vec<&Signature> = db.entries.iter().collect();
+    entries.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.name.cmp(&b.name)));
+
+    println!("{} {}", "Signatures:".bold(), entries.len());
+    println!("  {}", "prio  ext      name  (source)".dimmed());
+    for sig in entries {
+        print_entry(sig);
+        for rule in &sig.rules {
+            println!("                 {}", rule.to_string().dimmed());
+        }
+    }
+}
+
+fn test(db: &SignatureDb, file: &Path) -> Result<()> {
+    let buf = read_header(file)?;
+    println!("{} {}", "File:".bold(), file.display());
+    println!("{} {} bytes", "Header read:".bold(), buf.len());
+
+    let matches = db.matches(&buf);
+    if matches.is_empty() {
+        println!("\n{}", "No signature matched; the file is sorted by its extension.".yellow());
+        return Ok(());
+    }
+
+    println!("\n{}", "Matching signatures (best first):".bold());
+    for (sig, offsets) in &matches {
+        print_entry(sig);
+        for (rule, at) in sig.rules.iter().zip(offsets) {
+            println!("                 {} {}", rule.to_string().dimmed(), format!("matched at {}", at).green());
+        }
+    }
+
+    println!();
+    match db.best(&buf) {
+        Some(sig) => println!(
+            "{} .{} ({}, priority {})",
+            "Detected type:".green().bold(),
+            sig.ext,
+            sig.name,
+            sig.priority
+        ),
+        None => println!("{}", "Detected type: none (only binary signatures matched)".yellow()),
+    }
+    if db.is_binary(&buf) {
+        println!("{}", "Treated as a binary file: sorting asks before moving it.".yellow());
+    }
+    Ok(())
+}
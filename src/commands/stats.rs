@@ -0,0 +1,235 @@
+use anyhow::{Context, Result, bail};
+use colored::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::classify::Category;
+use crate::cli::Args;
+use crate::date::civil_from_time;
+use crate::detect::{ext_from_path, is_binary, resolve_extension};
+use crate::filter::{format_size, FileFilter};
+use crate::metadata::exec;
+use crate::{collect_files, ProcessingResult};
+
+#[derive(Debug, Default, Serialize)]
+struct Bucket {
+    name: String,
+    files: usize,
+    bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FileEntry {
+    path: String,
+    bytes: u64,
+    /// Seconds since the Unix epoch
+    modified: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Stats {
+    root: String,
+    files: usize,
+    bytes: u64,
+    categories: Vec<Bucket>,
+    top_extensions: Vec<Bucket>,
+    mismatched: usize,
+    binary: usize,
+    no_extension: usize,
+    filtered: usize,
+    largest: Vec<FileEntry>,
+    oldest: Vec<FileEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+/// `dir` and every directory below it; symlinked directories are not followed.
+fn walk(dir: &Path, dirs: &mut Vec<PathBuf>, errors: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => return errors.push(format!("cannot read dir {}: {}", dir.display(), err)),
+    };
+    dirs.push(dir.to_path_buf());
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.path())
+        .collect();
+    subdirs.sort();
+    for sub in subdirs {
+        walk(&sub, dirs, errors);
+    }
+}
+
+/// Buckets sorted by size, then count, then name.
+fn ranked(map: HashMap<String, Bucket>) -> Vec<Bucket> {
+    let mut buckets: Vec<Bucket> = map.into_values().collect();
+    buckets.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.files.cmp(&a.files)).then(a.name.cmp(&b.name)));
+    buckets
+}
+
+fn add(map: &mut HashMap<String, Bucket>, name: &str, bytes: u64) {
+    let bucket = map.entry(name.to_string()).or_insert_with(|| Bucket { name: name.to_string(), ..Default::default() });
+    bucket.files += 1;
+    bucket.bytes += bytes;
+}
+
+/// Classifies `path` the way a sort run would, without prompting.
+fn classify(path: &Path, args: &Args, stats: &mut Stats) -> Result<&'static str> {
+    let res = resolve_extension(path, args.ext_only, true)?;
+    if res.mismatch.is_some() {
+        stats.mismatched += 1;
+    }
+    if !args.ext_only && is_binary(path)? {
+        stats.binary += 1;
+    }
+    let ext = res.ext.unwrap_or_else(|| "unknown".to_string());
+    let mut category = Category::from_ext(&ext);
+    if !args.ext_only && matches!(category, Category::Uncategorized) && exec::inspect(path).is_some() {
+        category = Category::Executables;
+    }
+    Ok(category.dir_name())
+}
+
+fn collect(root: &Path, args: &Args, top: usize) -> Result<Stats> {
+    let mut stats = Stats { root: root.display().to_string(), ..Default::default() };
+    let mut dirs = Vec::new();
+    walk(root, &mut dirs, &mut stats.errors);
+
+    let filter = FileFilter::from_args(args);
+    let mut result = ProcessingResult::new();
+    let mut categories = HashMap::new();
+    let mut extensions = HashMap::new();
+    let mut files = Vec::new();
+
+    for dir in &dirs {
+        let entries = match collect_files(dir, &filter, args.symlinks, &mut result) {
+            Ok(entries) => entries,
+            Err(err) => {
+                stats.errors.push(format!("{:#}", err));
+                continue;
+            }
+        };
+        for path in entries {
+            let meta = match fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path)) {
+                Ok(meta) => meta,
+                Err(err) => {
+                    stats.errors.push(format!("cannot read {}: {}", path.display(), err));
+                    continue;
+                }
+            };
+            let category = match classify(&path, args, &mut stats) {
+                Ok(category) => category,
+                Err(err) => {
+                    stats.errors.push(format!("{:#}", err));
+                    continue;
+                }
+            };
+
+            let bytes = meta.len();
+            stats.files += 1;
+            stats.bytes += bytes;
+            add(&mut categories, category, bytes);
+            match ext_from_path(&path) {
+                Some(ext) => add(&mut extensions, &ext, bytes),
+                None => stats.no_extension += 1,
+            }
+            let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+            files.push(FileEntry {
+                path: path.strip_prefix(root).unwrap_or(&path).display().to_string(),
+                bytes,
+                modified: modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            });
+        }
+    }
+
+    stats.filtered = result.filtered.len();
+    stats.categories = ranked(categories);
+    let mut extensions = ranked(extensions);
+    extensions.sort_by(|a, b| b.files.cmp(&a.files).then(b.bytes.cmp(&a.bytes)).then(a.name.cmp(&b.name)));
+    extensions.truncate(top);
+    stats.top_extensions = extensions;
+
+    files.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.path.cmp(&b.path)));
+    stats.largest = files.iter().take(top).cloned().collect();
+    files.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+    stats.oldest = files.into_iter().take(top).collect();
+    Ok(stats)
+}
+
+fn date(secs: u64) -> String {
+    let (y, m, d) = civil_from_time(UNIX_EPOCH + Duration::from_secs(secs));
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+fn print_buckets(title: &str, buckets: &[Bucket], prefix: &str) {
+    if buckets.is_empty() {
+        return;
+    }
+    println!("\n{}", title.bold());
+    for b in buckets {
+        println!(
+            "  {:<16} {:>8} {:>9}",
+            format!("{}{}", prefix, b.name).cyan(),
+            b.files,
+            format_size(b.bytes).dimmed()
+        );
+    }
+}
+
+fn print_stats(stats: &Stats) {
+    println!("{} {}", "Statistics for".bold(), stats.root);
+    println!("  {:<16} {} ({})", "Files:", stats.files, format_size(stats.bytes));
+    if stats.filtered > 0 {
+        println!("  {:<16} {}", "Filtered out:", stats.filtered);
+    }
+
+    print_buckets("Categories:", &stats.categories, "");
+    print_buckets("Top extensions:", &stats.top_extensions, ".");
+
+    println!("\n{}", "Detection:".bold());
+    let mismatched = stats.mismatched.to_string();
+    println!(
+        "  {:<16} {}",
+        "Mismatched:",
+        if stats.mismatched > 0 { mismatched.yellow() } else { mismatched.normal() }
+    );
+    println!("  {:<16} {}", "Binary:", stats.binary);
+    println!("  {:<16} {}", "No extension:", stats.no_extension);
+
+    if !stats.largest.is_empty() {
+        println!("\n{}", "Largest files:".bold());
+        for f in &stats.largest {
+            println!("  {:>9}  {}", format_size(f.bytes), f.path);
+        }
+        println!("\n{}", "Oldest files:".bold());
+        for f in &stats.oldest {
+            println!("  {}  {}", date(f.modified).dimmed(), f.path);
+        }
+    }
+
+    if !stats.errors.is_empty() {
+        println!("\n{}", "Errors:".red().bold());
+        for err in &stats.errors {
+            println!("  {}", err.red());
+        }
+    }
+}
+
+pub fn run(dir: &Path, top: usize, args: &Args) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("not a directory: {}", dir.display());
+    }
+    let root = fs::canonicalize(dir).with_context(|| format!("cannot resolve {}", dir.display()))?;
+    let stats = collect(&root, args, top)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_stats(&stats);
+    }
+    Ok(())
+}
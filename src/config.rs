@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::paths::config_dir;
+use crate::updater::Channel;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdateConfig {
+    /// Check for updates on startup
+    pub check: bool,
+    /// Minimum time between checks, e.g. "1d" or "12h"
+    pub interval: String,
+    /// Connect/read timeout in seconds
+    pub timeout_secs: u64,
+    /// Release API base URL or a local release JSON file
+    pub source: Option<String>,
+    /// `"stable"`, `"beta"` or `"nightly"`; overrides the legacy `prerelease` switch
+    pub channel: Option<Channel>,
+    /// Asset name with `{version}`, `{target}`, `{os}`, `{arch}`, `{libc}`, `{ext}` placeholders
+    pub asset_pattern: Option<String>,
+    /// Legacy switch, same as `channel = "beta"`
+    pub prerelease: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            check: true,
+            interval: "1d".to_string(),
+            timeout_secs: 5,
+            source: None,
+            channel: None,
+            asset_pattern: None,
+            prerelease: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SidecarConfig {
+    /// Move companion files (subtitles, .xmp, .cue, ...) together with their primary file
+    pub enabled: bool,
+    /// Sidecar extension -> primary extensions in order of preference (`*` = any);
+    /// an empty list removes a built-in rule
+    pub rules: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for SidecarConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub updates: UpdateConfig,
+    pub sidecars: SidecarConfig,
+}
+
+impl Config {
+    /// Loads `path`, or `<config dir>/sortify/config.toml` when it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(p) => p.to_path_buf(),
+            None => match config_dir().map(|d| d.join("config.toml")) {
+                Some(p) if p.is_file() => p,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::Args;
+use crate::date::{days_from_civil, days_in_month};
+
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 30m, 2h, 7d)", s))?;
+
+    let secs = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => return Err(format!("unknown duration unit '{}'", other)),
+    };
+
+    value
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{}' is too large", s))
+}
+
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let invalid = || format!("invalid size '{}' (expected e.g. 512K, 10M, 2G)", s);
+    let (whole, frac) = num.split_once('.').unwrap_or((num, ""));
+    if (whole.is_empty() && frac.is_empty()) || frac.contains('.') {
+        return Err(invalid());
+    }
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+
+    let mult: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        other => return Err(format!("unknown size unit '{}'", other)),
+    };
+
+    // Integer arithmetic throughout: digits past the 19th cannot add a whole byte
+    let frac = &frac[..frac.len().min(19)];
+    let frac_bytes = if frac.is_empty() {
+        0
+    } else {
+        let digits: u128 = frac.parse().map_err(|_| invalid())?;
+        (digits * mult as u128 / 10u128.pow(frac.len() as u32)) as u64
+    };
+    whole
+        .checked_mul(mult)
+        .and_then(|bytes| bytes.checked_add(frac_bytes))
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// Human-readable size in the units `parse_size` accepts, e.g. `512B`, `1.5M`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1 << 10 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Accepts `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM[:SS]`, interpreted as UTC.
+pub fn parse_date(s: &str) -> Result<SystemTime, String> {
+    let err = || format!("invalid date '{}' (expected YYYY-MM-DD[THH:MM[:SS]])", s);
+    let s = s.trim();
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((d, t)) => (d, Some(t)),
+        None => (s, None),
+    };
+
+    let mut parts = date.split('-').map(|p| p.parse::<i64>());
+    let (y, m, d) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(y)), Some(Ok(m)), Some(Ok(d)), None) => (y, m, d),
+        _ => return Err(err()),
+    };
+    if !(0..=9999).contains(&y) || !(1..=12).contains(&m) || !(1..=days_in_month(y, m)).contains(&d) {
+        return Err(err());
+    }
+
+    let mut secs = days_from_civil(y, m, d) * 86400;
+    if let Some(t) = time {
+        let fields: Vec<i64> = t
+            .split(':')
+            .map(|p| p.parse::<i64>().map_err(|_| err()))
+            .collect::<Result<_, _>>()?;
+        let (h, min, sec) = match fields.as_slice() {
+            [h, min] => (*h, *min, 0),
+            [h, min, sec] => (*h, *min, *sec),
+            _ => return Err(err()),
+        };
+        if !(0..24).contains(&h) || !(0..60).contains(&min) || !(0..60).contains(&sec) {
+            return Err(err());
+        }
+        secs += h * 3600 + min * 60 + sec;
+    }
+
+    u64::try_from(secs)
+        .map(|s| UNIX_EPOCH + Duration::from_secs(s))
+        .map_err(|_| err())
+}
+
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    min_age: Option<Duration>,
+    max_age: Option<Duration>,
+    newer_than: Option<SystemTime>,
+    older_than: Option<SystemTime>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+}
+
+impl FileFilter {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            min_age: args.min_age,
+            max_age: args.max_age,
+            newer_than: args.newer_than,
+            older_than: args.older_than,
+            min_size: args.min_size,
+            max_size: args.max_size,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.min_age.is_some()
+            || self.max_age.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some()
+            || self.min_size.is_some()
+            || self.max_size.is_some()
+    }
+
+    /// Returns the reason the file should be left alone, if any.
+    pub fn check(&self, path: &Path, now: SystemTime) -> Result<Option<String>> {
+        if !self.is_active() {
+            return Ok(None);
+        }
+
+        // A dangling link has no target to measure, so the link itself is checked
+        let meta = fs::metadata(path)
+            .or_else(|_| fs::symlink_metadata(path))
+            .with_context(|| format!("cannot read metadata of {}", path.display()))?;
+        let size = meta.len();
+
+        if let Some(min) = self.min_size.filter(|&min| size < min) {
+            return Ok(Some(format!("smaller than --min-size ({} < {} bytes)", size, min)));
+        }
+        if let Some(max) = self.max_size.filter(|&max| size > max) {
+            return Ok(Some(format!("larger than --max-size ({} > {} bytes)", size, max)));
+        }
+
+        let needs_mtime = self.min_age.is_some()
+            || self.max_age.is_some()
+            || self.newer_than.is_some()
+            || self.older_than.is_some();
+        if !needs_mtime {
+            return Ok(None);
+        }
+
+        let mtime = meta
+            .modified()
+            .with_context(|| format!("cannot read mtime of {}", path.display()))?;
+        let age = now.duration_since(mtime).unwrap_or(Duration::ZERO);
+
+        if let Some(min) = self.min_age.filter(|&min| age < min) {
+            return Ok(Some(format!(
+                "modified too recently (--min-age {}s, age {}s)",
+                min.as_secs(),
+                age.as_secs()
+            )));
+        }
+        if let Some(max) = self.max_age.filter(|&max| age > max) {
+            return Ok(Some(format!(
+                "modified too long ago (--max-age {}s, age {}s)",
+                max.as_secs(),
+                age.as_secs()
+            )));
+        }
+        if self.newer_than.is_some_and(|t| mtime <= t) {
+            return Ok(Some("not newer than --newer-than".to_string()));
+        }
+        if self.older_than.is_some_and(|t| mtime >= t) {
+            return Ok(Some("not older than --older-than".to_string()));
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> u64 {
+        parse_date(s).unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn durations_take_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration(" 2H "), Ok(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_duration("1w"), Ok(Duration::from_secs(7 * 86400)));
+    }
+
+    #[test]
+    fn durations_reject_garbage_and_overflow() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("5y").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("99999999999999999w").unwrap_err().contains("too large"));
+        assert!(parse_duration("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn sizes_take_binary_units() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512b"), Ok(512));
+        assert_eq!(parse_size("1K"), Ok(1024));
+        assert_eq!(parse_size("1.5M"), Ok(1536 * 1024));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size("1t"), Ok(1 << 40));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1.2.3K").is_err());
+        assert!(parse_size(".").is_err());
+    }
+
+    #[test]
+    fn sizes_are_exact_and_reject_overflow() {
+        assert_eq!(parse_size("0.5K"), Ok(512));
+        assert_eq!(parse_size(".25M"), Ok(256 * 1024));
+        assert_eq!(parse_size("9007199254740993"), Ok(9_007_199_254_740_993));
+        assert_eq!(parse_size("16777215.999999999999T"), Ok(u64::MAX - 1));
+        assert!(parse_size("99999999999T").unwrap_err().contains("too large"));
+        assert!(parse_size("16777216T").unwrap_err().contains("too large"));
+        assert!(parse_size("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn sizes_round_trip_through_format() {
+        assert_eq!(format_size(1023), "1023B");
+        assert_eq!(format_size(1024), "1.0K");
+        assert_eq!(format_size(1536 * 1024), "1.5M");
+        assert_eq!(format_size(u64::MAX), "16777216.0T");
+    }
+
+    #[test]
+    fn dates_are_utc() {
+        assert_eq!(date("1970-01-01"), 0);
+        assert_eq!(date("2024-02-29"), 1_709_164_800);
+        assert_eq!(date("2024-02-29T12:30"), 1_709_164_800 + 12 * 3600 + 30 * 60);
+        assert_eq!(date("2024-02-29 23:59:59"), 1_709_164_800 + 86399);
+    }
+
+    #[test]
+    fn dates_reject_out_of_range_fields() {
+        for bad in [
+            "2023-02-29",
+            "2024-02-30",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-01-00",
+            "2024-01-01T24:00",
+            "2024-01-01T99:00",
+            "2024-01-01T12:75",
+            "2024-01-01T12:00:60",
+            "2024-01-01T12",
+            "1969-12-31",
+            "99999999999-01-01",
+            "2024/01/01",
+        ] {
+            assert!(parse_date(bad).is_err(), "{} was accepted", bad);
+        }
+    }
+}
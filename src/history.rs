@@ -0,0 +1,509 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ProcessingResult;
+use crate::date::civil_from_time;
+use crate::ops::{TransferMode, make_symlink, move_file, set_mode, untag};
+use crate::paths::state_dir;
+
+/// One filesystem change made by a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// A file was moved; `mode` holds permissions that were changed afterwards (quarantine)
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<u32>,
+    },
+    /// A copy, link or clone of `from` was placed at `to` (`--mode`); the original stayed put
+    Copied {
+        from: PathBuf,
+        to: PathBuf,
+        kind: TransferMode,
+        /// `to` as it was placed; undo leaves it alone once it no longer matches
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stamp: Option<Stamp>,
+    },
+    /// A file was unpacked from `archive`
+    Extracted { archive: PathBuf, to: PathBuf },
+    /// An archive was deleted after extraction (`--delete-extracted`)
+    Deleted { path: PathBuf },
+    /// A symbolic link was removed after its target was copied into place (`--symlinks=follow-target`)
+    Unlinked { link: PathBuf, target: PathBuf },
+}
+
+/// Size and modification time (nanoseconds since the epoch) of a file, not following links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamp {
+    pub size: u64,
+    pub modified: u64,
+}
+
+impl Stamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let meta = fs::symlink_metadata(path).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self { size: meta.len(), modified: modified.as_nanos() as u64 })
+    }
+}
+
+impl JournalEntry {
+    pub fn moved(from: &Path, to: &Path) -> Self {
+        JournalEntry::Moved { from: from.to_path_buf(), to: to.to_path_buf(), mode: None }
+    }
+
+    /// `moved`, or `Copied` for the modes that leave the original in place.
+    pub fn placed(from: &Path, to: &Path, kind: TransferMode) -> Self {
+        match kind {
+            TransferMode::Move => Self::moved(from, to),
+            _ => JournalEntry::Copied { from: from.to_path_buf(), to: to.to_path_buf(), kind, stamp: Stamp::of(to) },
+        }
+    }
+}
+
+/// Everything kept about one sort run, stored as `<state dir>/sortify/history/<id>.json`.
+#[derive(Serialize, Deserialize)]
+pub struct RunRecord {
+    /// `YYYYMMDD-HHMMSS` of the start time (UTC), with `-2`, `-3`, ... for runs in the same second
+    #[serde(default)]
+    pub id: String,
+    /// Seconds since the Unix epoch
+    pub started: u64,
+    pub finished: u64,
+    pub root: PathBuf,
+    pub command_line: Vec<String>,
+    pub dry_run: bool,
+    #[serde(flatten)]
+    pub result: ProcessingResult,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(default)]
+    pub journal: Vec<JournalEntry>,
+    /// When the run was undone, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undone: Option<u64>,
+}
+
+/// What `RunRecord::undo` managed to revert.
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    pub restored: Vec<String>,
+    /// Extracted files, copies and links that were deleted again
+    pub removed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+pub fn timestamp(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn format_time(secs: u64) -> String {
+    let (y, m, d) = civil_from_time(UNIX_EPOCH + Duration::from_secs(secs));
+    let t = secs % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", y, m, d, t / 3600, t / 60 % 60, t % 60)
+}
+
+fn history_dir() -> Result<PathBuf> {
+    let dir = state_dir().context("cannot determine the state directory (set XDG_STATE_HOME or HOME)")?;
+    Ok(dir.join("history"))
+}
+
+fn record_path(dir: &Path, id: &str) -> Result<PathBuf> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        bail!("invalid run id '{}'", id);
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+/// Removes `path`'s parent directories up to `root` while they are empty.
+fn prune_parents(path: &Path, root: &Path) {
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d == root || !d.starts_with(root) || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+fn restore(from: &Path, to: &Path, mode: Option<u32>) -> Result<()> {
+    // symlink_metadata, so that moved links whose target is gone still count
+    if fs::symlink_metadata(to).is_err() {
+        bail!("{} is no longer there", to.display());
+    }
+    if fs::symlink_metadata(from).is_ok() {
+        bail!("{} already exists; leaving {} in place", from.display(), to.display());
+    }
+    if let Some(dir) = from.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("cannot create dir {}", dir.display()))?;
+    }
+    move_file(to, from).with_context(|| format!("cannot move {} back", to.display()))?;
+    if !from.is_symlink() {
+        untag(from);
+    }
+    if let Some(mode) = mode {
+        set_mode(from, mode)?;
+    }
+    Ok(())
+}
+
+impl RunRecord {
+    pub fn new(started: SystemTime, root: &Path, dry_run: bool, mut result: ProcessingResult, error: Option<String>) -> Self {
+        Self {
+            id: String::new(),
+            started: timestamp(started),
+            finished: timestamp(SystemTime::now()),
+            root: root.to_path_buf(),
+            command_line: std::env::args().collect(),
+            dry_run,
+            journal: std::mem::take(&mut result.journal),
+            result,
+            errors: error.into_iter().collect(),
+            undone: None,
+        }
+    }
+
+    /// Writes the record, picking an id first if it has none.
+    pub fn save(&mut self) -> Result<()> {
+        self.save_in(&history_dir()?)
+    }
+
+    fn save_in(&mut self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("cannot create dir {}", dir.display()))?;
+
+        if self.id.is_empty() {
+            let (y, m, d) = civil_from_time(UNIX_EPOCH + Duration::from_secs(self.started));
+            let t = self.started % 86400;
+            let base = format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, m, d, t / 3600, t / 60 % 60, t % 60);
+            self.id = (1..)
+                .map(|i| match i {
+                    1 => base.clone(),
+                    _ => format!("{}-{}", base, i),
+                })
+                .find(|id| !dir.join(format!("{}.json", id)).exists())
+                .unwrap_or(base);
+        }
+
+        let path = record_path(dir, &self.id)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("cannot write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("cannot write {}", path.display()))
+    }
+
+    /// Loads a run by id; `last` is the most recent one.
+    pub fn load(id: &str) -> Result<Self> {
+        Self::load_from(&history_dir()?, id)
+    }
+
+    fn load_from(dir: &Path, id: &str) -> Result<Self> {
+        if id == "last" {
+            return Self::all_in(dir)?.pop().context("no runs recorded yet");
+        }
+        let path = record_path(dir, id)?;
+        if !path.exists() {
+            bail!("no run with id '{}' (see `sortify history`)", id);
+        }
+        let data = fs::read(&path).with_context(|| format!("cannot read {}", path.display()))?;
+        serde_json::from_slice(&data).with_context(|| format!("cannot parse {}", path.display()))
+    }
+
+    /// All recorded runs, oldest first. Unreadable records are skipped.
+    pub fn all() -> Result<Vec<Self>> {
+        Self::all_in(&history_dir()?)
+    }
+
+    fn all_in(dir: &Path) -> Result<Vec<Self>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records: Vec<Self> = fs::read_dir(dir)
+            .with_context(|| format!("cannot read dir {}", dir.display()))?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .filter_map(|p| fs::read(&p).ok())
+            .filter_map(|data| serde_json::from_slice(&data).ok())
+            .collect();
+        records.sort_by(|a, b| a.started.cmp(&b.started).then(a.id.cmp(&b.id)));
+        Ok(records)
+    }
+
+    /// Reverts the run's changes newest first. Files that were changed since are left alone
+    /// and reported; the record is marked as undone either way.
+    pub fn undo(&mut self) -> Result<UndoReport> {
+        self.undo_in(&history_dir()?)
+    }
+
+    fn undo_in(&mut self, dir: &Path) -> Result<UndoReport> {
+        if self.dry_run {
+            bail!("run {} was a dry run; there is nothing to undo", self.id);
+        }
+        if let Some(at) = self.undone {
+            bail!("run {} was already undone at {}", self.id, format_time(at));
+        }
+
+        let mut report = UndoReport::default();
+        for change in self.journal.iter().rev() {
+            match change {
+                JournalEntry::Moved { from, to, mode } => match restore(from, to, *mode) {
+                    Ok(()) => {
+                        report.restored.push(format!("{} → {}", to.display(), from.display()));
+                        prune_parents(to, &self.root);
+                    }
+                    Err(err) => report.failed.push(format!("{:#}", err)),
+                },
+                JournalEntry::Copied { to, stamp, .. } => {
+                    if fs::symlink_metadata(to).is_err() {
+                        report.failed.push(format!("{} is no longer there", to.display()));
+                    } else if stamp.is_some_and(|stamp| Stamp::of(to) != Some(stamp)) {
+                        report.failed.push(format!("{} was changed since it was placed; leaving it", to.display()));
+                    } else if let Err(err) = fs::remove_file(to) {
+                        report.failed.push(format!("cannot remove {}: {}", to.display(), err));
+                    } else {
+                        report.removed.push(to.display().to_string());
+                        prune_parents(to, &self.root);
+                    }
+                }
+                JournalEntry::Extracted { archive, to } => {
+                    if !archive.exists() {
+                        report.failed.push(format!(
+                            "kept {}: its archive {} is not back in place",
+                            to.display(),
+                            archive.display()
+                        ));
+                    } else if let Err(err) = fs::remove_file(to) {
+                        report.failed.push(format!("cannot remove {}: {}", to.display(), err));
+                    } else {
+                        report.removed.push(to.display().to_string());
+                        prune_parents(to, &self.root);
+                    }
+                }
+                JournalEntry::Unlinked { link, target } => {
+                    let relinked = match fs::symlink_metadata(link) {
+                        Ok(_) => Err(anyhow!("{} already exists; not relinking it", link.display())),
+                        Err(_) => make_symlink(target, link),
+                    };
+                    match relinked {
+                        Ok(()) => report.restored.push(format!("relinked {} -> {}", link.display(), target.display())),
+                        Err(err) => report.failed.push(format!("{:#}", err)),
+                    }
+                }
+                JournalEntry::Deleted { path } => {
+                    report.failed.push(format!("{} was deleted and cannot be restored", path.display()));
+                }
+            }
+        }
+
+        self.undone = Some(timestamp(SystemTime::now()));
+        self.save_in(dir)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-history-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn record(root: &Path, started: u64, dry_run: bool, journal: Vec<JournalEntry>) -> RunRecord {
+        let mut result = ProcessingResult::new();
+        result.journal = journal;
+        RunRecord::new(UNIX_EPOCH + Duration::from_secs(started), root, dry_run, result, None)
+    }
+
+    #[test]
+    fn restore_moves_files_back_and_refuses_to_overwrite() {
+        let dir = scratch("restore");
+        let (from, to) = (dir.join("gone/sub/a.txt"), dir.join("Documents/a.txt"));
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::write(&to, "a").unwrap();
+
+        restore(&from, &to, Some(0o640)).unwrap();
+        assert_eq!(fs::read_to_string(&from).unwrap(), "a");
+        assert!(!to.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&from).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+
+        let err = restore(&from, &to, None).unwrap_err();
+        assert!(err.to_string().contains("no longer there"));
+
+        fs::write(&to, "newer").unwrap();
+        let err = restore(&from, &to, None).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert_eq!(fs::read_to_string(&to).unwrap(), "newer");
+        assert_eq!(fs::read_to_string(&from).unwrap(), "a");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn undo_reverts_moves_copies_and_extractions() {
+        let dir = scratch("undo");
+        let state = dir.join(".state");
+        let root = dir.join("root");
+        fs::create_dir_all(root.join("Documents")).unwrap();
+        fs::create_dir_all(root.join("Pictures/2024")).unwrap();
+        fs::create_dir_all(root.join("Archives")).unwrap();
+
+        // Moved: a.txt went to Documents/
+        fs::write(root.join("Documents/a.txt"), "a").unwrap();
+        // Copied: b.jpg stayed, with a copy in Pictures/2024/
+        fs::write(root.join("b.jpg"), "b").unwrap();
+        fs::copy(root.join("b.jpg"), root.join("Pictures/2024/b.jpg")).unwrap();
+        // Extracted: c.txt came out of the archive, which is back in place
+        fs::write(root.join("Archives/c.zip"), "zip").unwrap();
+        fs::write(root.join("Documents/c.txt"), "c").unwrap();
+
+        let mut run = record(
+            &root,
+            1_700_000_000,
+            false,
+            vec![
+                JournalEntry::moved(&root.join("a.txt"), &root.join("Documents/a.txt")),
+                JournalEntry::placed(&root.join("b.jpg"), &root.join("Pictures/2024/b.jpg"), TransferMode::Copy),
+                JournalEntry::Extracted { archive: root.join("Archives/c.zip"), to: root.join("Documents/c.txt") },
+            ],
+        );
+        run.save_in(&state).unwrap();
+
+        let report = run.undo_in(&state).unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.restored.len(), 1);
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("b.jpg")).unwrap(), "b");
+        assert!(!root.join("Documents").exists());
+        assert!(!root.join("Pictures").exists());
+        assert!(root.is_dir());
+
+        // The stored record says it was undone, so a second undo refuses
+        let mut again = RunRecord::load_from(&state, &run.id).unwrap();
+        assert!(again.undone.is_some());
+        assert!(again.undo_in(&state).unwrap_err().to_string().contains("already undone"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn undo_leaves_changed_copies_and_orphaned_extractions() {
+        let dir = scratch("undo-changed");
+        let state = dir.join(".state");
+        fs::create_dir_all(dir.join("Documents")).unwrap();
+        let (original, copy) = (dir.join("notes.txt"), dir.join("Documents/notes.txt"));
+        fs::write(&original, "v1").unwrap();
+        fs::copy(&original, &copy).unwrap();
+        let placed = JournalEntry::placed(&original, &copy, TransferMode::Copy);
+        // Edited since the run: different size
+        fs::write(&copy, "v2, with edits").unwrap();
+
+        let extracted = dir.join("Documents/inner.txt");
+        fs::write(&extracted, "x").unwrap();
+        let orphan = JournalEntry::Extracted { archive: dir.join("Archives/gone.zip"), to: extracted.clone() };
+
+        let mut run = record(&dir, 1_700_000_000, false, vec![placed, orphan]);
+        let report = run.undo_in(&state).unwrap();
+        assert_eq!(report.failed.len(), 2, "{:?}", report.failed);
+        assert!(report.failed.iter().any(|f| f.contains("was changed since it was placed")));
+        assert!(report.failed.iter().any(|f| f.contains("is not back in place")));
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "v2, with edits");
+        assert!(extracted.exists());
+
+        // Records written before stamps existed still remove their copies
+        let legacy: JournalEntry = serde_json::from_str(&format!(
+            r#"{{"op":"copied","from":{:?},"to":{:?},"kind":"copy"}}"#,
+            original, copy
+        ))
+        .unwrap();
+        let mut run = record(&dir, 1_700_000_001, false, vec![legacy]);
+        let report = run.undo_in(&state).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert!(!copy.exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn stamps_notice_same_size_rewrites() {
+        let dir = scratch("stamp");
+        let path = dir.join("f");
+        fs::write(&path, "aaaa").unwrap();
+        let before = Stamp::of(&path).unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert_ne!(Stamp::of(&path), Some(before));
+        assert_eq!(Stamp::of(&dir.join("missing")), None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn dry_runs_are_recorded_but_not_undone() {
+        let dir = scratch("dry");
+        let state = dir.join(".state");
+        let mut run = record(&dir, 1_700_000_000, true, vec![]);
+        run.save_in(&state).unwrap();
+        let mut loaded = RunRecord::load_from(&state, "last").unwrap();
+        assert!(loaded.dry_run);
+        assert!(loaded.undo_in(&state).unwrap_err().to_string().contains("dry run"));
+        assert!(RunRecord::load_from(&state, &run.id).unwrap().undone.is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn runs_list_oldest_first_with_unique_ids() {
+        let dir = scratch("list");
+        let state = dir.join("history");
+        assert!(RunRecord::all_in(&state).unwrap().is_empty());
+        assert!(RunRecord::load_from(&state, "last").is_err());
+
+        let mut first = record(&dir, 1_700_000_000, false, vec![]);
+        let mut second = record(&dir, 1_700_000_000, false, vec![]);
+        let mut later = record(&dir, 1_700_000_100, true, vec![]);
+        later.save_in(&state).unwrap();
+        first.save_in(&state).unwrap();
+        second.save_in(&state).unwrap();
+        assert_eq!(first.id, "20231114-221320");
+        assert_eq!(second.id, "20231114-221320-2");
+        fs::write(state.join("broken.json"), "{").unwrap();
+
+        let ids: Vec<_> = RunRecord::all_in(&state).unwrap().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, [first.id.as_str(), second.id.as_str(), later.id.as_str()]);
+        assert_eq!(RunRecord::load_from(&state, "last").unwrap().id, later.id);
+        assert_eq!(RunRecord::load_from(&state, &second.id).unwrap().started, 1_700_000_000);
+
+        assert!(RunRecord::load_from(&state, "20000101-000000").err().unwrap().to_string().contains("no run"));
+        for bad in ["", "../x", "a/b", ".hidden"] {
+            assert!(RunRecord::load_from(&state, bad).err().unwrap().to_string().contains("invalid run id"));
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn undo_relinks_followed_links() {
+        let dir = scratch("relink");
+        let link = dir.join("shortcut.txt");
+        let target = dir.join("elsewhere/real.txt");
+        let mut run = record(&dir, 1_700_000_000, false, vec![JournalEntry::Unlinked { link: link.clone(), target: target.clone() }]);
+        let report = run.undo_in(&dir.join(".state")).unwrap();
+        assert_eq!(report.restored.len(), 1);
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+
+        let mut run = record(&dir, 1_700_000_001, false, vec![JournalEntry::Unlinked { link: link.clone(), target }]);
+        let report = run.undo_in(&dir.join(".state")).unwrap();
+        assert!(report.failed[0].contains("already exists"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::classify::Category;
+use crate::cli::Args;
+use crate::learn::{Learned, MIN_FOLDERS, Model};
+use crate::metadata::{self, Metadata};
+use crate::output;
+use crate::template::Template;
+
+pub const DEFAULT_AUDIO_LAYOUT: &str = "{artist}/{album}/[{track:02} - ]{title}.{ext}";
+pub const DEFAULT_PHOTO_LAYOUT: &str = "{year}/{year}-{month}-{day}";
+pub const DEFAULT_VIDEO_LAYOUT: &str = "{year}/{year}-{month}-{day}";
+pub const DEFAULT_EXEC_LAYOUT: &str = "{platform}[-{arch}][/{exec_group}]";
+
+const PAIRED_PHOTO_EXTS: &[&str] = &["jpg", "jpeg", "heic", "heif"];
+const RAW_SIBLING_EXTS: &[&str] = &["cr2", "cr3", "nef", "arw", "dng", "raf", "orf", "rw2", "raw"];
+
+/// Per-category destination templates, relative to the category folder.
+#[derive(Debug, Default)]
+pub struct Layouts {
+    audio: Option<Template>,
+    photo: Option<Template>,
+    video: Option<Template>,
+    exec: Option<Template>,
+    clip_max: Duration,
+    /// Read format metadata even when no template needs it (for the JSON report)
+    probe_all: bool,
+    /// (source dir, lowercase stem) -> chosen folder, so RAW+JPEG siblings land together
+    photo_pairs: HashMap<(PathBuf, String), PathBuf>,
+    /// `--learn-from`: folders of an existing tree proposed ahead of the category folders
+    pub learned: Option<Learned>,
+}
+
+fn pair_key(path: &Path) -> (PathBuf, String) {
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    (dir, stem)
+}
+
+fn lower_ext(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// A RAW file next to `path` with the same stem, if there is one.
+fn raw_sibling(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_string_lossy().into_owned();
+    let dir = path.parent()?;
+    RAW_SIBLING_EXTS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_ascii_uppercase()])
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .find(|p| p.is_file())
+}
+
+impl Layouts {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            audio: args.audio_layout.clone(),
+            photo: args.photo_layout.clone(),
+            video: args.video_layout.clone(),
+            exec: args.exec_layout.clone(),
+            clip_max: args.clip_max,
+            probe_all: args.json || args.verbose > 0,
+            photo_pairs: HashMap::new(),
+            learned: None,
+        }
+    }
+
+    /// Scans the `--learn-from` tree, if one was given. Files directly in `sorting` are not learned from.
+    pub fn learn(mut self, args: &Args, sorting: &Path) -> Result<Self> {
+        if let Some(dir) = &args.learn_from {
+            let model = Model::scan(dir, sorting)?;
+            if model.size().0 < MIN_FOLDERS {
+                output::info(format!(
+                    "{} has fewer than {} folders with files; nothing will be proposed from it",
+                    dir.display(),
+                    MIN_FOLDERS
+                ));
+            }
+            self.learned = Some(Learned::new(model, args.confidence));
+        }
+        Ok(self)
+    }
+
+    /// Path of the file relative to the sort root, e.g. `Audio/Artist/Album/01 - Title.mp3`,
+    /// together with the metadata that was read to decide it.
+    pub fn destination(&mut self, path: &Path, ext: &str, category: &Category) -> Result<(PathBuf, Metadata)> {
+        let dir = PathBuf::from(category.dir_name());
+        let file_name = path.file_name().map(PathBuf::from).unwrap_or_default();
+        let base = metadata::base(path, ext, category);
+
+        let (rendered, meta) = match category {
+            Category::Audio if self.audio.is_some() || self.probe_all => {
+                let meta = metadata::audio(path, base);
+                let rel = self.audio.as_ref().map(|t| t.render(&meta)).transpose()?;
+                (rel, meta)
+            }
+            Category::Pictures if self.photo.is_some() => {
+                let meta = metadata::photo(path, base);
+                let folder = self.photo_folder(path, ext, category, &meta)?;
+                (Some(folder.join(&file_name)), meta)
+            }
+            Category::Pictures if self.probe_all => (None, metadata::photo(path, base)),
+            Category::Video if self.video.is_some() || self.probe_all => {
+                let meta = metadata::video(path, base, self.clip_max);
+                let folder = self.video.as_ref().map(|t| t.render(&meta)).transpose()?;
+                (folder.map(|f| f.join(&file_name)), meta)
+            }
+            Category::Executables if self.exec.is_some() || self.probe_all => {
+                let meta = metadata::exec(path, base);
+                let folder = self.exec.as_ref().map(|t| t.render(&meta)).transpose()?;
+                (folder.map(|f| f.join(&file_name)), meta)
+            }
+            _ => (None, base),
+        };
+
+        let dest = match rendered {
+            Some(rel) => dir.join(rel),
+            None => dir.join(file_name),
+        };
+        Ok((dest, meta))
+    }
+
+    /// Folder inside `Pictures/` for a photo. Non-camera images go to `Screenshots` or `Edited`;
+    /// RAW and JPEG/HEIC files sharing a stem reuse whichever folder was decided first,
+    /// preferring the RAW file's EXIF when both are still present and it names a camera.
+    fn photo_folder(&mut self, path: &Path, ext: &str, category: &Category, meta: &Metadata) -> Result<PathBuf> {
+        let key = pair_key(path);
+        if let Some(folder) = self.photo_pairs.get(&key) {
+            return Ok(folder.clone());
+        }
+
+        let lower = lower_ext(path);
+        let is_pairable = Category::is_raw_photo(&lower) || PAIRED_PHOTO_EXTS.contains(&lower.as_str());
+        let source = match PAIRED_PHOTO_EXTS.contains(&lower.as_str()) {
+            true => raw_sibling(path),
+            false => None,
+        };
+
+        let raw_meta = source
+            .map(|raw| metadata::photo(&raw, metadata::base(&raw, ext, category)))
+            .filter(|raw| raw.get("photo_kind").is_some_and(|k| k == "camera"));
+        let meta = raw_meta.as_ref().unwrap_or(meta);
+
+        let folder = match meta.get("photo_kind").map(String::as_str) {
+            Some("screenshot") => PathBuf::from("Screenshots"),
+            Some("edited") => PathBuf::from("Edited"),
+            _ => match &self.photo {
+                Some(t) => t.render(meta)?,
+                None => PathBuf::new(),
+            },
+        };
+
+        if is_pairable {
+            self.photo_pairs.insert(key, folder.clone());
+        }
+        Ok(folder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::exif::tests::{jpeg, tiff};
+    use std::fs;
+
+    fn photo_layouts() -> Layouts {
+        Layouts {
+            photo: Some(Template::parse("{year}/{camera}").unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-layout-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn raw_sibling_exif_decides_the_pair() {
+        let dir = scratch("raw");
+        let photo = dir.join("IMG_1.jpg");
+        fs::write(&photo, jpeg(&tiff(b"II*\0", "Canon", "Phone", "2020:01:01 00:00:00"))).unwrap();
+        fs::write(dir.join("IMG_1.ORF"), tiff(b"IIRO", "OLYMPUS", "E-M1", "2024:05:01 10:00:00")).unwrap();
+
+        let (dest, _) = photo_layouts().destination(&photo, "jpg", &Category::Pictures).unwrap();
+        assert_eq!(dest, Path::new("Pictures/2024/OLYMPUS E-M1/IMG_1.jpg"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn jpeg_exif_is_used_when_the_raw_has_none() {
+        let dir = scratch("fallback");
+        let photo = dir.join("IMG_2.jpg");
+        fs::write(&photo, jpeg(&tiff(b"MM\0*", "Canon", "Canon EOS R5", "2024:05:01 10:00:00"))).unwrap();
+        fs::write(dir.join("IMG_2.raw"), b"not a tiff").unwrap();
+
+        let mut layouts = photo_layouts();
+        let (dest, _) = layouts.destination(&photo, "jpg", &Category::Pictures).unwrap();
+        assert_eq!(dest, Path::new("Pictures/2024/Canon EOS R5/IMG_2.jpg"));
+
+        let (raw, _) = layouts.destination(&dir.join("IMG_2.raw"), "raw", &Category::Pictures).unwrap();
+        assert_eq!(raw, Path::new("Pictures/2024/Canon EOS R5/IMG_2.raw"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::classify::Category;
+use crate::detect::ext_from_path;
+use crate::prompt::{self, LearnedAction};
+
+/// Proposals below this are more likely wrong than right and are never made.
+const MIN_CONFIDENCE: f64 = 0.5;
+/// With a single folder to choose from, every file would be proposed for it with full confidence.
+pub const MIN_FOLDERS: usize = 2;
+
+/// What one folder of the learned tree holds.
+#[derive(Debug, Default)]
+struct Folder {
+    /// Relative to the tree's root
+    path: PathBuf,
+    files: usize,
+    exts: HashMap<String, usize>,
+    categories: HashMap<&'static str, usize>,
+    tokens: HashMap<String, usize>,
+    token_total: usize,
+    sizes: HashMap<u32, usize>,
+}
+
+/// Which extensions, categories, name tokens and sizes live where in a hand-organised tree.
+/// New files are scored against every folder as a naive Bayes classifier would.
+#[derive(Debug, Default)]
+pub struct Model {
+    root: PathBuf,
+    folders: Vec<Folder>,
+    files: usize,
+    ext_kinds: usize,
+    token_kinds: usize,
+    size_kinds: usize,
+}
+
+/// A learned folder for a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Proposal {
+    pub folder: PathBuf,
+    /// 50-100, in percent
+    pub confidence: u8,
+}
+
+/// Lowercase words of a file name; numbers only count when they look like a year (1900-2099).
+fn tokens(path: &Path) -> Vec<String> {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let mut tokens: Vec<String> = stem
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2)
+        .filter(|t| !t.chars().all(|c| c.is_ascii_digit()) || t.parse().is_ok_and(|y: u32| (1900..2100).contains(&y)))
+        .map(str::to_string)
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+/// Sizes in steps of four: 1-3 bytes, 4-15, 16-63, ...
+fn size_bucket(bytes: u64) -> u32 {
+    bytes.max(1).ilog2() / 2
+}
+
+/// `(count + 1) / (total + kinds)`, in log space.
+fn smoothed(count: usize, total: usize, kinds: usize) -> f64 {
+    ((count as f64 + 1.0) / (total as f64 + kinds.max(1) as f64)).ln()
+}
+
+impl Model {
+    /// Learns from every folder below `root` that holds files. Hidden entries, symlinked
+    /// folders and the files directly in `exclude` (the folder being sorted) are left out.
+    pub fn scan(root: &Path, exclude: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            bail!("not a directory: {}", root.display());
+        }
+        let root = fs::canonicalize(root).with_context(|| format!("cannot resolve {}", root.display()))?;
+        let exclude = fs::canonicalize(exclude).unwrap_or_else(|_| exclude.to_path_buf());
+
+        let mut model = Model { root: root.clone(), ..Default::default() };
+        let mut pending = vec![root.clone()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut folder = Folder {
+                path: dir.strip_prefix(&root).unwrap_or(&dir).to_path_buf(),
+                ..Default::default()
+            };
+            for entry in entries.filter_map(Result::ok) {
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let Ok(kind) = entry.file_type() else {
+                    continue;
+                };
+                let path = entry.path();
+                if kind.is_dir() {
+                    pending.push(path);
+                } else if kind.is_file() && dir != root && dir != exclude {
+                    let bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    folder.add(&path, bytes);
+                }
+            }
+            if folder.files > 0 {
+                model.folders.push(folder);
+            }
+        }
+
+        model.folders.sort_by(|a, b| a.path.cmp(&b.path));
+        model.files = model.folders.iter().map(|f| f.files).sum();
+        model.ext_kinds = model.kinds(|f| f.exts.keys().cloned().collect());
+        model.token_kinds = model.kinds(|f| f.tokens.keys().cloned().collect());
+        model.size_kinds = model.kinds(|f| f.sizes.keys().map(u32::to_string).collect());
+        Ok(model)
+    }
+
+    fn kinds(&self, keys: impl Fn(&Folder) -> Vec<String>) -> usize {
+        let mut all: Vec<String> = self.folders.iter().flat_map(keys).collect();
+        all.sort();
+        all.dedup();
+        all.len()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of folders and files learned from.
+    pub fn size(&self) -> (usize, usize) {
+        (self.folders.len(), self.files)
+    }
+
+    /// The most likely folder for `path`, if the tree says anything about files like it.
+    /// The winner must hold files with the same extension or a shared name token, and there
+    /// must be at least `MIN_FOLDERS` to choose from.
+    pub fn propose(&self, path: &Path, ext: &str, category: &Category) -> Option<Proposal> {
+        if self.folders.len() < MIN_FOLDERS {
+            return None;
+        }
+        let ext = ext_from_path(path).unwrap_or_else(|| ext.to_string());
+        let tokens = tokens(path);
+        // Words never seen in the tree say nothing about where a file goes
+        let known: Vec<&String> = tokens
+            .iter()
+            .filter(|t| self.folders.iter().any(|f| f.tokens.contains_key(*t)))
+            .collect();
+        let bucket = size_bucket(fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+
+        let scores: Vec<f64> = self
+            .folders
+            .iter()
+            .map(|f| {
+                smoothed(f.files, self.files, self.folders.len())
+                    + smoothed(f.exts.get(&ext).copied().unwrap_or(0), f.files, self.ext_kinds)
+                    + smoothed(f.categories.get(category.dir_name()).copied().unwrap_or(0), f.files, Category::ALL.len())
+                    + smoothed(f.sizes.get(&bucket).copied().unwrap_or(0), f.files, self.size_kinds)
+                    + known
+                        .iter()
+                        .map(|t| smoothed(f.tokens.get(*t).copied().unwrap_or(0), f.token_total, self.token_kinds))
+                        .sum::<f64>()
+            })
+            .collect();
+
+        let (best, top) = scores.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+        let total: f64 = scores.iter().map(|s| (s - top).exp()).sum();
+        let confidence = 1.0 / total;
+
+        let folder = &self.folders[best];
+        let evidence = folder.exts.contains_key(&ext) || tokens.iter().any(|t| folder.tokens.contains_key(t));
+        (evidence && confidence >= MIN_CONFIDENCE).then(|| Proposal {
+            folder: self.root.join(&folder.path),
+            confidence: (confidence * 100.0).floor() as u8,
+        })
+    }
+}
+
+impl Folder {
+    fn add(&mut self, path: &Path, bytes: u64) {
+        let ext = ext_from_path(path).unwrap_or_default();
+        self.files += 1;
+        *self.categories.entry(Category::from_ext(&ext).dir_name()).or_default() += 1;
+        *self.exts.entry(ext).or_default() += 1;
+        for token in tokens(path) {
+            *self.tokens.entry(token).or_default() += 1;
+            self.token_total += 1;
+        }
+        *self.sizes.entry(size_bucket(bytes)).or_default() += 1;
+    }
+}
+
+/// What a run does with a proposal.
+#[derive(Debug)]
+pub enum Decision {
+    Use(Proposal),
+    Usual,
+    Skip,
+}
+
+/// `--learn-from` with the `--confidence` threshold, or the answers given so far.
+#[derive(Debug)]
+pub struct Learned {
+    pub model: Model,
+    /// Percent at which proposals are applied without asking
+    pub threshold: Option<u8>,
+    accept_all: bool,
+}
+
+impl Learned {
+    pub fn new(model: Model, threshold: Option<u8>) -> Self {
+        Self { model, threshold, accept_all: false }
+    }
+
+    /// Whether `proposal` is used without asking.
+    pub fn applies(&self, proposal: &Proposal) -> bool {
+        self.accept_all || self.threshold.is_some_and(|t| proposal.confidence >= t)
+    }
+
+    /// Where `path` goes. Without a threshold, each proposal is confirmed interactively;
+    /// a dry run shows every proposal in the plan without asking.
+    pub fn decide(&mut self, path: &Path, ext: &str, category: &Category, usual: &Path, dry_run: bool) -> Result<Decision> {
+        let Some(proposal) = self.model.propose(path, ext, category) else {
+            return Ok(Decision::Usual);
+        };
+        if self.applies(&proposal) || (dry_run && self.threshold.is_none()) {
+            return Ok(Decision::Use(proposal));
+        }
+        if dry_run || self.threshold.is_some() {
+            return Ok(Decision::Usual);
+        }
+
+        let shown = proposal.folder.strip_prefix(self.model.root()).unwrap_or(&proposal.folder);
+        Ok(match prompt::ask_learned_destination(path, shown, proposal.confidence, usual)? {
+            LearnedAction::Accept => Decision::Use(proposal),
+            LearnedAction::AcceptAll => {
+                self.accept_all = true;
+                Decision::Use(proposal)
+            }
+            LearnedAction::Usual => Decision::Usual,
+            LearnedAction::Skip => Decision::Skip,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-learn-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files(root: &Path, paths: &[&str]) {
+        for rel in paths {
+            let path = root.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "x".repeat(2000)).unwrap();
+        }
+    }
+
+    fn propose(model: &Model, path: &Path) -> Option<Proposal> {
+        let ext = path.extension().unwrap().to_string_lossy().into_owned();
+        model.propose(path, &ext, &Category::from_ext(&ext))
+    }
+
+    #[test]
+    fn tokens_are_words_and_years() {
+        assert_eq!(tokens(Path::new("Contract_ACME-2023 v2.pdf")), ["2023", "acme", "contract", "v2"]);
+        assert_eq!(tokens(Path::new("IMG_0001.jpg")), ["img"]);
+        assert_eq!(tokens(Path::new("a b 12 tax tax.pdf")), ["tax"]);
+        assert!(tokens(Path::new("x_12.pdf")).is_empty());
+    }
+
+    #[test]
+    fn sizes_fall_in_steps_of_four() {
+        assert_eq!([0, 1, 3, 4, 15, 16, 63, 64].map(size_bucket), [0, 0, 0, 1, 1, 2, 2, 3]);
+        assert_eq!(size_bucket(1 << 20), 10);
+        assert_eq!(size_bucket(u64::MAX), 31);
+    }
+
+    #[test]
+    fn proposals_follow_the_curated_tree() {
+        let dir = scratch("tree");
+        let tree = dir.join("tree");
+        files(
+            &tree,
+            &[
+                "Work/Contracts/contract_acme_2023.pdf",
+                "Work/Contracts/contract_globex_2024.pdf",
+                "Work/Contracts/nda_acme.pdf",
+                "Personal/Taxes/tax_return_2023.pdf",
+                "Personal/Taxes/w2_2023.pdf",
+                "Personal/Taxes/tax_receipts_2023.xlsx",
+                "Personal/Photos/beach.jpg",
+                "Personal/Photos/sunset.jpg",
+                // Loose files at the root and hidden folders teach nothing
+                "loose_contract.pdf",
+                ".git/contract_objects.pdf",
+                // The folder being sorted is never a destination
+                "Inbox/contract_draft.pdf",
+                "Inbox/contract_other.pdf",
+            ],
+        );
+        let model = Model::scan(&tree, &tree.join("Inbox")).unwrap();
+        assert_eq!(model.size(), (3, 8));
+
+        files(&dir, &["contract_initech_2024.pdf", "tax_return_2024.pdf", "holiday.jpg", "song.mp3"]);
+        let contract = propose(&model, &dir.join("contract_initech_2024.pdf")).unwrap();
+        assert_eq!(contract.folder, model.root().join("Work/Contracts"));
+        assert!((50..=100).contains(&contract.confidence), "{}", contract.confidence);
+
+        let taxes = propose(&model, &dir.join("tax_return_2024.pdf")).unwrap();
+        assert_eq!(taxes.folder, model.root().join("Personal/Taxes"));
+        assert_eq!(propose(&model, &dir.join("holiday.jpg")).unwrap().folder, model.root().join("Personal/Photos"));
+
+        // Nothing like it in the tree: no extension or word in common
+        assert!(propose(&model, &dir.join("song.mp3")).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn uncertain_or_unopposed_proposals_are_not_made() {
+        let dir = scratch("uncertain");
+        // Three folders that look the same: at best a one-in-three guess
+        let tree = dir.join("tree");
+        files(&tree, &["A/report_q1.pdf", "B/report_q2.pdf", "C/report_q3.pdf"]);
+        let model = Model::scan(&tree, &dir).unwrap();
+        files(&dir, &["report_q4.pdf"]);
+        assert!(propose(&model, &dir.join("report_q4.pdf")).is_none());
+
+        // A lone folder would win every file with full confidence
+        let single = dir.join("single");
+        files(&single, &["Work/report_2023.pdf", "Work/report_2024.pdf"]);
+        let model = Model::scan(&single, &dir).unwrap();
+        assert_eq!(model.size(), (1, 2));
+        assert!(propose(&model, &dir.join("report_q4.pdf")).is_none());
+
+        assert!(Model::scan(&dir.join("missing"), &dir).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
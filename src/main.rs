@@ -1,43 +1,175 @@
+mod archive;
 mod cli;
 mod detect;
 mod classify;
+mod commands;
+mod config;
+mod date;
+mod filter;
+mod history;
+mod layout;
+mod learn;
+mod metadata;
+mod multipart;
 mod ops;
+mod output;
+mod paths;
 mod prompt;
+mod safety;
+mod sidecar;
+mod signatures;
+mod template;
 mod updater;
 
 use anyhow::{Context, Result};
 use clap::Parser;
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
 
-use crate::cli::Args;
-use crate::detect::{is_binary, resolve_extension};
+use crate::archive::extract::{self, Kind};
+use crate::cli::{Args, Command};
+use crate::detect::{detect, is_binary, read_header, resolve_extension, Detection};
 use crate::classify::Category;
-use crate::ops::move_to_category;
+use crate::config::Config;
+use crate::filter::FileFilter;
+use crate::history::{JournalEntry, RunRecord};
+use crate::layout::Layouts;
+use crate::learn::Decision;
+use crate::metadata::Metadata;
+use crate::multipart::VolumeSet;
+use crate::output::{Verbosity, verbosity};
+use crate::ops::{SymlinkPolicy, TransferMode, file_mode, move_all, move_to_category, strip_permissions};
 use crate::prompt::{BinaryAction, BinaryPolicy};
-use crate::updater::check_for_updates;
+use crate::sidecar::SidecarRules;
+use crate::signatures::SignatureDb;
+use crate::updater::{UpdateSettings, check_for_updates, self_update};
 
+// Exit statuses, documented in `--help`; clap exits with 2 on a bad command line.
+const EXIT_ERROR: u8 = 1;
+const EXIT_NOTHING_TO_DO: u8 = 3;
+const EXIT_SKIPPED: u8 = 4;
+const EXIT_FAILED: u8 = 5;
+
+#[derive(Serialize, Deserialize)]
+struct MovedFile {
+    source: String,
+    destination: String,
+    category: String,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    metadata: Metadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    companion_of: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    part_of: Option<String>,
+    /// `--learn-from`: confidence in the learned folder, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    learned: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FilteredFile {
+    path: String,
+    reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QuarantinedFile {
+    source: String,
+    destination: String,
+    reasons: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FailedFile {
+    path: String,
+    error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IncompleteSet {
+    name: String,
+    volumes: Vec<String>,
+    reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
 struct ProcessingResult {
-    moved: Vec<(String, String)>,
+    /// `--mode`: whether `moved` were moved, copied or linked
+    #[serde(default)]
+    mode: TransferMode,
+    moved: Vec<MovedFile>,
+    quarantined: Vec<QuarantinedFile>,
     skipped: Vec<String>,
+    filtered: Vec<FilteredFile>,
+    incomplete: Vec<IncompleteSet>,
+    #[serde(default)]
+    failed: Vec<FailedFile>,
+    /// Links, FIFOs, sockets and devices that were left alone
+    #[serde(default)]
+    ignored: Vec<FilteredFile>,
     warnings: Vec<String>,
+    /// Filesystem changes in the order they were made, for `history undo`
+    #[serde(skip)]
+    journal: Vec<JournalEntry>,
 }
 
 impl ProcessingResult {
     fn new() -> Self {
         Self {
+            mode: TransferMode::Move,
             moved: Vec::new(),
+            quarantined: Vec::new(),
             skipped: Vec::new(),
+            filtered: Vec::new(),
+            incomplete: Vec::new(),
+            failed: Vec::new(),
+            ignored: Vec::new(),
             warnings: Vec::new(),
+            journal: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.moved.is_empty()
+            && self.quarantined.is_empty()
+            && self.skipped.is_empty()
+            && self.filtered.is_empty()
+            && self.incomplete.is_empty()
+            && self.failed.is_empty()
+            && self.ignored.is_empty()
+            && self.warnings.is_empty()
+    }
+
+    /// Records a per-file error and carries on, or hands it back with `--fail-fast`.
+    fn fail(&mut self, path: &Path, err: anyhow::Error, fail_fast: bool) -> Result<()> {
+        if fail_fast {
+            return Err(err);
+        }
+        self.failed.push(FailedFile {
+            path: path.display().to_string(),
+            error: format!("{:#}", err),
+        });
+        Ok(())
+    }
+
+    /// Notes a filesystem change for the run history; dry runs change nothing.
+    fn record(&mut self, dry_run: bool, entry: JournalEntry) {
+        if !dry_run {
+            self.journal.push(entry);
         }
     }
 }
 
 fn print_banner() {
     let version = env!("CARGO_PKG_VERSION");
-    println!(
+    eprintln!(
         "{} v{}\n{}",
         "[ Sortify ]".bright_cyan().bold(),
         version,
@@ -46,22 +178,158 @@ fn print_banner() {
     );
 }
 
-fn is_self_binary(entry: &PathBuf, exe: &Option<PathBuf>) -> bool {
+fn is_self_binary(entry: &Path, exe: &Option<PathBuf>) -> bool {
     exe.as_ref().is_some_and(|p| p == entry)
 }
 
-fn collect_files(cwd: &PathBuf) -> Result<Vec<PathBuf>> {
-    let entries: Vec<_> = fs::read_dir(cwd)
-        .context("cannot read current directory")?
+/// Why a symbolic link is not sorted under `policy`, if it is not.
+fn link_ignored(path: &Path, policy: SymlinkPolicy) -> Option<String> {
+    let target = fs::metadata(path).ok();
+    match (policy, target) {
+        (SymlinkPolicy::Skip, _) => Some("symbolic link (see --symlinks)".to_string()),
+        (_, Some(meta)) if meta.is_dir() => Some("symbolic link to a folder".to_string()),
+        (_, Some(meta)) if !meta.is_file() => Some("symbolic link to a special file".to_string()),
+        (SymlinkPolicy::FollowTarget, None) => Some("dangling symbolic link".to_string()),
+        _ => None,
+    }
+}
+
+fn collect_files(
+    cwd: &Path,
+    filter: &FileFilter,
+    symlinks: SymlinkPolicy,
+    result: &mut ProcessingResult,
+) -> Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(cwd)
+        .with_context(|| format!("cannot read dir {}", cwd.display()))?
         .filter_map(Result::ok)
-        .map(|e| e.path())
-        .filter(|p| p.is_file())
-        .collect();
+    {
+        let path = entry.path();
+        let Ok(kind) = entry.file_type() else {
+            continue;
+        };
+        let ignored = if kind.is_dir() {
+            continue;
+        } else if kind.is_symlink() {
+            link_ignored(&path, symlinks)
+        } else if !kind.is_file() {
+            Some(ops::special_kind(&kind).to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = ignored {
+            result.ignored.push(FilteredFile {
+                path: path.display().to_string(),
+                reason,
+            });
+            continue;
+        }
+
+        // A file that vanished or became unreadable since the listing is reported, not fatal
+        match filter.check(&path, now) {
+            Ok(Some(reason)) => result.filtered.push(FilteredFile {
+                path: path.display().to_string(),
+                reason,
+            }),
+            Ok(None) => entries.push(path),
+            Err(err) => result.warnings.push(format!("Cannot check {}: {:#}", path.display(), err)),
+        }
+    }
 
     Ok(entries)
 }
 
+/// `--tag`: writes provenance xattrs on every file the run put in place. The category is
+/// the top folder a file landed in; extracted files name their archive as the source.
+fn tag_sorted(cwd: &Path, result: &mut ProcessingResult) {
+    let mut failed = Vec::new();
+    for change in &result.journal {
+        let (source, target) = match change {
+            JournalEntry::Moved { from, to, .. } => (from, to),
+            // A hard link shares its attributes with the original, which must stay untouched
+            JournalEntry::Copied { kind: TransferMode::Hardlink, .. } => continue,
+            JournalEntry::Copied { from, to, .. } => (from, to),
+            JournalEntry::Extracted { archive, to } => (archive, to),
+            _ => continue,
+        };
+        // Links cannot carry user attributes on Linux
+        if target.is_symlink() {
+            continue;
+        }
+        // Learned folders may lie outside the sorted directory; those name their own folder
+        let category = target
+            .strip_prefix(cwd)
+            .ok()
+            .and_then(|rel| rel.components().next())
+            .map(|c| c.as_os_str())
+            .or_else(|| target.parent().and_then(Path::file_name))
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Err(err) = ops::tag(target, &category, source) {
+            failed.push((target.display().to_string(), err));
+        }
+    }
+
+    if let Some((path, err)) = failed.first() {
+        result.warnings.push(format!(
+            "Could not tag {} file(s), e.g. {}: {}",
+            failed.len(),
+            path,
+            err
+        ));
+    }
+}
+
+/// Warns about files that share their data with another name, since moving one name
+/// leaves the others behind.
+fn warn_hard_links(entries: &[PathBuf], result: &mut ProcessingResult) {
+    let mut groups: HashMap<(u64, u64), (u64, Vec<&PathBuf>)> = HashMap::new();
+    for entry in entries {
+        if let Ok(meta) = fs::symlink_metadata(entry)
+            && let Some((id, links)) = ops::hard_link(&meta)
+        {
+            groups.entry(id).or_insert((links, Vec::new())).1.push(entry);
+        }
+    }
+
+    let mut groups: Vec<_> = groups.into_values().collect();
+    for (_, names) in &mut groups {
+        names.sort();
+    }
+    groups.sort_by(|a, b| a.1.cmp(&b.1));
+    for (links, names) in groups {
+        let shown: Vec<String> = names.iter().map(|p| p.display().to_string()).collect();
+        let outside = links.saturating_sub(names.len() as u64);
+        result.warnings.push(match (names.len(), outside) {
+            (1, _) => format!("Hard link: {} has {} other name(s) outside this folder", shown[0], outside),
+            (_, 0) => format!("Hard links to the same data: {}", shown.join(", ")),
+            (_, _) => format!(
+                "Hard links to the same data: {} (and {} more outside this folder)",
+                shown.join(", "),
+                outside
+            ),
+        });
+    }
+}
+
+/// Destination relative to the sort root; just the folder when the file kept its name.
+fn shown_destination(target: &Path, cwd: &Path, source: &Path) -> String {
+    let shown = target.strip_prefix(cwd).unwrap_or(target);
+    let shown = if shown.file_name() == source.file_name() {
+        shown.parent().unwrap_or(shown)
+    } else {
+        shown
+    };
+    shown.display().to_string()
+}
+
 fn create_progress_bar(total: u64) -> ProgressBar {
+    if !output::progress_enabled() {
+        return ProgressBar::hidden();
+    }
     let pb = ProgressBar::new(total);
     pb.set_style(
         ProgressStyle::with_template("{spinner:.green} {msg:.bold.dimmed} [{pos}/{len}]")
@@ -73,21 +341,44 @@ fn create_progress_bar(total: u64) -> ProgressBar {
 
 fn process_file(
     entry: PathBuf,
-    cwd: &PathBuf,
+    cwd: &Path,
     current_exe: &Option<PathBuf>,
     policy: &mut BinaryPolicy,
     args: &Args,
+    layouts: &mut Layouts,
     result: &mut ProcessingResult,
-) -> Result<()> {
+) -> Result<Option<(PathBuf, Category)>> {
     let canonical = fs::canonicalize(&entry).unwrap_or_else(|_| entry.clone());
 
     if is_self_binary(&canonical, current_exe) {
         result.skipped.push(entry.display().to_string());
-        return Ok(());
+        return Ok(None);
+    }
+
+    let is_link = fs::symlink_metadata(&entry).is_ok_and(|m| m.file_type().is_symlink());
+    // `--symlinks=follow-target` works on the file behind the link, which stays where it is:
+    // a move copies it and removes the link only once the copy is in place
+    let follow = is_link && args.symlinks == SymlinkPolicy::FollowTarget;
+    let (source, mode) = match (follow, args.mode) {
+        (true, TransferMode::Move) => (canonical.clone(), TransferMode::Copy),
+        (true, mode) => (canonical.clone(), mode),
+        (false, mode) => (entry.clone(), mode),
+    };
+    // A dangling link (`--symlinks=move-link`) has no contents to look at
+    let ext_only = args.ext_only || !entry.exists();
+
+    if args.quarantine {
+        let reasons = safety::assess(&entry);
+        if !reasons.is_empty() {
+            return quarantine(entry, &source, mode, reasons, cwd, args, result).map(Some);
+        }
     }
 
-    let res = resolve_extension(&entry, args.ext_only, args.dry_run)?;
+    let res = resolve_extension(&entry, ext_only, args.dry_run)?;
     let ext_opt = res.ext;
+    if let Some(ext) = &ext_opt {
+        trace_detection(&entry, ext, ext_only);
+    }
 
     if let Some((sig, real)) = res.mismatch {
         result.warnings.push(format!(
@@ -102,53 +393,469 @@ fn process_file(
         Some(e) => e,
         None => {
             result.skipped.push(entry.display().to_string());
-            return Ok(());
+            return Ok(None);
         }
     };
 
-    if !args.ext_only && is_binary(&entry)? {
+    if !ext_only && is_binary(&entry)? {
         if args.dry_run {
             result
                 .warnings
                 .push(format!("Binary file detected: {}", entry.display()));
             result.skipped.push(entry.display().to_string());
-            return Ok(());
+            return Ok(None);
         }
 
-        let (action, new_policy) = policy.decide(&entry)?;
+        let details = metadata::exec::inspect(&entry).map(|info| info.to_string());
+        let (action, new_policy) = policy.decide(&entry, details.as_deref())?;
         *policy = new_policy;
 
         if let BinaryAction::Skip = action {
+            output::trace(format!("{}: skipped binary file", entry.display()).dimmed());
             result.skipped.push(entry.display().to_string());
-            return Ok(());
+            return Ok(None);
+        }
+    }
+
+    let mut category = Category::from_ext(&ext);
+    // Extensionless binaries and runnable scripts belong with the executables
+    if !ext_only && matches!(category, Category::Uncategorized) && metadata::exec::inspect(&entry).is_some() {
+        category = Category::Executables;
+    }
+    let contents = match category {
+        Category::Archives if args.inspect_archives => archive::inspect(&entry, &ext).unwrap_or_else(|err| {
+            result
+                .warnings
+                .push(format!("Cannot inspect archive {}: {}", entry.display(), err));
+            None
+        }),
+        _ => None,
+    };
+
+    let mut learned = None;
+    let (dest, mut meta) = match contents.as_ref().and_then(|c| c.placement()) {
+        Some((folder, sub)) => {
+            category = folder;
+            let file_name = entry.file_name().unwrap_or_default();
+            let dest = Path::new(folder.dir_name()).join(sub).join(file_name);
+            (dest, Metadata::new())
+        }
+        None => {
+            let (usual, meta) = layouts.destination(&entry, &ext, &category)?;
+            let decision = match &mut layouts.learned {
+                Some(learned) => learned.decide(&entry, &ext, &category, &usual, args.dry_run)?,
+                None => Decision::Usual,
+            };
+            match decision {
+                Decision::Use(proposal) => {
+                    learned = Some(proposal.confidence);
+                    (proposal.folder.join(entry.file_name().unwrap_or_default()), meta)
+                }
+                Decision::Usual => (usual, meta),
+                Decision::Skip => {
+                    result.skipped.push(entry.display().to_string());
+                    return Ok(None);
+                }
+            }
+        }
+    };
+    if let Some(contents) = &contents {
+        contents.describe(&mut meta);
+        result.warnings.extend(contents.executable_warning(&entry));
+    }
+    let target = move_to_category(&source, cwd, &dest, mode, args.dry_run)
+        .with_context(|| format!("failed to {} {}", args.mode.verb(), entry.display()))?;
+    result.record(args.dry_run, JournalEntry::placed(&entry, &target, mode));
+    if follow {
+        if args.mode == TransferMode::Move && !args.dry_run {
+            drop_followed_link(&entry, result)?;
         }
+    } else if is_link && !args.dry_run && let Some(dir) = entry.parent() {
+        ops::keep_link_target(&target, dir)?;
+    }
+    output::trace(format!("{}: → {}", entry.display(), target.display()).dimmed());
+
+    for key in ["name", "stem", "ext", "category"] {
+        meta.remove(key);
+    }
+    result.moved.push(MovedFile {
+        source: entry.display().to_string(),
+        destination: shown_destination(&target, cwd, &entry),
+        category: category.dir_name().to_string(),
+        metadata: meta,
+        companion_of: None,
+        part_of: None,
+        learned,
+    });
+    Ok(Some((target, category)))
+}
+
+/// `-vv`: which detector decided the extension a file is sorted by.
+fn trace_detection(entry: &Path, ext: &str, ext_only: bool) {
+    if verbosity() < Verbosity::Trace {
+        return;
+    }
+    let how = match read_header(entry).map(|buf| detect(&buf)) {
+        _ if ext_only => "extension (--ext-only)".to_string(),
+        Ok(Detection { signature: Some(sig), .. }) => format!("signature \"{}\"", sig.name),
+        Ok(detection) => detection.detector.name().to_string(),
+        Err(err) => format!("extension (header unreadable: {})", err),
+    };
+    output::trace(format!("{}: .{} by {}", entry.display(), ext, how).dimmed());
+}
+
+/// Moves a risky file to `Quarantine/` and leaves it read-only with no execute bits.
+/// With `--mode=hardlink` or `symlink` it is copied instead, so the original keeps its permissions.
+/// `source` is what gets transferred with `how`: `entry` itself, or the target of a followed link.
+fn quarantine(
+    entry: PathBuf,
+    source: &Path,
+    how: TransferMode,
+    reasons: Vec<String>,
+    cwd: &Path,
+    args: &Args,
+    result: &mut ProcessingResult,
+) -> Result<(PathBuf, Category)> {
+    let file_name = entry.file_name().unwrap_or_default();
+    let dest = Path::new(Category::Quarantine.dir_name()).join(file_name);
+    let how = match how {
+        TransferMode::Hardlink | TransferMode::Symlink => TransferMode::Copy,
+        how => how,
+    };
+    let mode = file_mode(&entry);
+    let target = move_to_category(source, cwd, &dest, how, args.dry_run)
+        .with_context(|| format!("failed to quarantine {}", entry.display()))?;
+    // chmod on a link would change the file it points to
+    if !args.dry_run && !target.is_symlink() {
+        strip_permissions(&target)?;
+    }
+    let change = match how {
+        TransferMode::Move => JournalEntry::Moved { from: entry.clone(), to: target.clone(), mode },
+        _ => JournalEntry::placed(&entry, &target, how),
+    };
+    result.record(args.dry_run, change);
+    if source != entry && args.mode == TransferMode::Move && !args.dry_run {
+        drop_followed_link(&entry, result)?;
+    }
+
+    result.quarantined.push(QuarantinedFile {
+        source: entry.display().to_string(),
+        destination: shown_destination(&target, cwd, &entry),
+        reasons,
+    });
+    Ok((target, Category::Quarantine))
+}
+
+/// Removes a followed link once a copy of its target is in place (`--symlinks=follow-target`
+/// with a move), recording where it pointed so undo can put it back.
+fn drop_followed_link(link: &Path, result: &mut ProcessingResult) -> Result<()> {
+    let target = fs::read_link(link).with_context(|| format!("cannot read link {}", link.display()))?;
+    fs::remove_file(link).with_context(|| format!("cannot remove link {}", link.display()))?;
+    output::trace(format!("{}: removed link to {}", link.display(), target.display()).dimmed());
+    result.record(false, JournalEntry::Unlinked { link: link.to_path_buf(), target });
+    Ok(())
+}
+
+/// Moves sidecars next to where their primary file went, renaming them if the primary was renamed.
+fn move_sidecars(
+    primary: &Path,
+    placed: Option<&(PathBuf, Category)>,
+    sidecars: &[PathBuf],
+    cwd: &Path,
+    args: &Args,
+    result: &mut ProcessingResult,
+) -> Result<()> {
+    let Some((target, category)) = placed else {
+        result
+            .skipped
+            .extend(sidecars.iter().map(|p| p.display().to_string()));
+        return Ok(());
+    };
+
+    let dir = target.parent().unwrap_or(cwd);
+    let dir = dir.strip_prefix(cwd).unwrap_or(dir);
+
+    for sidecar in sidecars {
+        let dest = dir.join(sidecar::follow_name(sidecar, primary, target));
+        let moved = match move_to_category(sidecar, cwd, &dest, args.mode, args.dry_run) {
+            Ok(moved) => moved,
+            Err(err) => {
+                let err = err.context(format!("failed to {} {}", args.mode.verb(), sidecar.display()));
+                result.fail(sidecar, err, args.fail_fast)?;
+                continue;
+            }
+        };
+        result.record(args.dry_run, JournalEntry::placed(sidecar, &moved, args.mode));
+
+        result.moved.push(MovedFile {
+            source: sidecar.display().to_string(),
+            destination: shown_destination(&moved, cwd, sidecar),
+            category: category.dir_name().to_string(),
+            metadata: Metadata::new(),
+            companion_of: Some(primary.display().to_string()),
+            part_of: None,
+            learned: None,
+        });
+    }
+    Ok(())
+}
+
+/// Moves all volumes of a multi-part set into one folder, or reports the set if volumes are missing.
+/// A name collision renames the whole set so the volumes still find each other.
+fn process_volume_set(set: &VolumeSet, cwd: &Path, args: &Args, result: &mut ProcessingResult) -> Result<()> {
+    let name = set.name();
+    if let Some(reason) = set.missing() {
+        result.incomplete.push(IncompleteSet {
+            name,
+            volumes: set.paths().map(|p| p.display().to_string()).collect(),
+            reason,
+        });
+        return Ok(());
     }
 
-    let category = Category::from_ext(&ext);
-    move_to_category(&entry, cwd, &category, args.dry_run)
-        .with_context(|| format!("failed to move {}", entry.display()))?;
+    let category = set.category();
+    let dir = cwd.join(category.dir_name());
+    let names = (0..10000)
+        .map(|i| match i {
+            0 => set.renamed(str::to_string),
+            _ => set.renamed(|base| format!("{}_{}", base, i)),
+        })
+        .find(|names| names.iter().all(|n| !dir.join(n).exists()))
+        .context("no free name for volume set")?;
+
+    let moves: Vec<(PathBuf, PathBuf)> = set
+        .paths()
+        .zip(&names)
+        .map(|(src, name)| (src.to_path_buf(), dir.join(name)))
+        .collect();
+    move_all(&moves, args.mode, args.dry_run)
+        .with_context(|| format!("failed to {} volume set {}", args.mode.verb(), name))?;
 
-    result
-        .moved
-        .push((entry.display().to_string(), category.dir_name().to_string()));
+    for (src, target) in moves {
+        result.record(args.dry_run, JournalEntry::placed(&src, &target, args.mode));
+        result.moved.push(MovedFile {
+            source: src.display().to_string(),
+            destination: shown_destination(&target, cwd, &src),
+            category: category.dir_name().to_string(),
+            metadata: Metadata::new(),
+            companion_of: None,
+            part_of: Some(name.clone()),
+            learned: None,
+        });
+    }
     Ok(())
 }
 
+fn staging_dir(cwd: &Path, archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let base = cwd.join(format!(".sortify-extract-{}", name));
+    (1..)
+        .map(|i| match i {
+            1 => base.clone(),
+            _ => PathBuf::from(format!("{}-{}", base.display(), i)),
+        })
+        .find(|p| !p.exists())
+        .unwrap_or(base)
+}
+
+/// Unpacks a supported archive and sorts its files like any others. The archive itself then goes
+/// to `Archives/Extracted` (or is deleted); if anything from it was left unsorted, it stays put.
+/// Returns `false` for unsupported formats and for archives that could not be extracted safely;
+/// those are sorted as regular files.
+fn extract_archive(
+    entry: &Path,
+    cwd: &Path,
+    current_exe: &Option<PathBuf>,
+    policy: &mut BinaryPolicy,
+    args: &Args,
+    layouts: &mut Layouts,
+    result: &mut ProcessingResult,
+) -> Result<bool> {
+    let ext = entry
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let Some(kind) = Kind::detect(entry, &ext) else {
+        return Ok(false);
+    };
+    let archive_name = entry.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+    if args.dry_run {
+        let listing = match extract::preview(entry, kind) {
+            Ok(listing) => listing,
+            Err(err) => {
+                result
+                    .warnings
+                    .push(format!("Cannot extract {}: {}", entry.display(), err));
+                return Ok(false);
+            }
+        };
+        for item in listing.entries.iter().filter(|e| !e.is_dir) {
+            if item.is_link {
+                result
+                    .warnings
+                    .push(format!("Link not extracted from {}: {}", archive_name, item.name));
+                continue;
+            }
+            let ext = Path::new(&item.name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            let category = Category::from_ext(&ext);
+            result.moved.push(MovedFile {
+                source: format!("{}:{}", archive_name, item.name),
+                destination: category.dir_name().to_string(),
+                category: category.dir_name().to_string(),
+                metadata: Metadata::new(),
+                companion_of: None,
+                part_of: None,
+                learned: None,
+            });
+        }
+        if !args.delete_extracted {
+            result.moved.push(MovedFile {
+                source: entry.display().to_string(),
+                destination: format!("{}/Extracted", Category::Archives.dir_name()),
+                category: Category::Archives.dir_name().to_string(),
+                metadata: Metadata::new(),
+                companion_of: None,
+                part_of: None,
+                learned: None,
+            });
+        }
+        return Ok(true);
+    }
+
+    let staging = staging_dir(cwd, entry);
+    let extracted = match extract::extract(entry, kind, &staging) {
+        Ok(extracted) => extracted,
+        Err(err) => {
+            // The staging dir is not there when the archive could not even be opened
+            if let Err(cleanup) = fs::remove_dir_all(&staging)
+                && cleanup.kind() != io::ErrorKind::NotFound
+            {
+                return Err(cleanup).with_context(|| format!("cannot remove staging dir {}", staging.display()));
+            }
+            result
+                .warnings
+                .push(format!("Cannot extract {}: {:#}", entry.display(), err));
+            return Ok(false);
+        }
+    };
+    for link in &extracted.skipped_links {
+        result
+            .warnings
+            .push(format!("Link not extracted from {}: {}", archive_name, link));
+    }
+
+    let first_moved = result.moved.len();
+    let first_skipped = result.skipped.len();
+    let first_failed = result.failed.len();
+    let first_change = result.journal.len();
+    // Staged files are the run's own; they are always moved out, whatever --mode says
+    let staged = Args { mode: TransferMode::Move, ..args.clone() };
+    for file in extracted.files {
+        if let Err(err) = process_file(file.clone(), cwd, current_exe, policy, &staged, layouts, result) {
+            result.fail(&file, err, args.fail_fast)?;
+        }
+    }
+
+    // Show extracted files as `archive.zip:inner/path` rather than by their staging path
+    let staging_prefix = format!("{}{}", staging.display(), std::path::MAIN_SEPARATOR);
+    let relabel = |source: &mut String| {
+        if let Some(inner) = source.strip_prefix(&staging_prefix) {
+            *source = format!("{}:{}", archive_name, inner);
+        }
+    };
+    result.moved[first_moved..].iter_mut().for_each(|m| relabel(&mut m.source));
+    result.skipped[first_skipped..].iter_mut().for_each(relabel);
+    result.failed[first_failed..].iter_mut().for_each(|f| relabel(&mut f.path));
+    for change in &mut result.journal[first_change..] {
+        if let JournalEntry::Moved { from, to, .. } = change
+            && from.starts_with(&staging)
+        {
+            *change = JournalEntry::Extracted { archive: entry.to_path_buf(), to: to.clone() };
+        }
+    }
+
+    if result.skipped.len() > first_skipped || result.failed.len() > first_failed {
+        result.warnings.push(format!(
+            "Some files from {} were not sorted and remain in {}",
+            archive_name,
+            staging.display()
+        ));
+        return Ok(true);
+    }
+
+    fs::remove_dir_all(&staging)
+        .with_context(|| format!("cannot remove staging dir {}", staging.display()))?;
+    if args.delete_extracted {
+        fs::remove_file(entry).with_context(|| format!("cannot delete {}", entry.display()))?;
+        result.record(false, JournalEntry::Deleted { path: entry.to_path_buf() });
+    } else {
+        let dest = Path::new(Category::Archives.dir_name()).join("Extracted").join(&archive_name);
+        let target = move_to_category(entry, cwd, &dest, args.mode, false)
+            .with_context(|| format!("failed to {} {}", args.mode.verb(), entry.display()))?;
+        result.record(false, JournalEntry::placed(entry, &target, args.mode));
+        result.moved.push(MovedFile {
+            source: entry.display().to_string(),
+            destination: shown_destination(&target, cwd, entry),
+            category: Category::Archives.dir_name().to_string(),
+            metadata: Metadata::new(),
+            companion_of: None,
+            part_of: None,
+            learned: None,
+        });
+    }
+    Ok(true)
+}
+
 fn print_results(result: &ProcessingResult, is_dry_run: bool) {
-    println!("{}", "Sorting completed".green().bold());
-    println!();
+    if !result.quarantined.is_empty() {
+        let heading = if is_dry_run { "Would quarantine:" } else { "Quarantined (review before opening):" };
+        println!("{}", heading.bright_red().bold());
+        for file in &result.quarantined {
+            println!(
+                "  {} {} {}",
+                safety::escape_bidi(&file.source).bold(),
+                "→".bright_black(),
+                safety::escape_bidi(&file.destination).red().bold()
+            );
+            for reason in &file.reasons {
+                println!("    {} {}", "!".bright_red().bold(), reason);
+            }
+        }
+        println!();
+    }
 
     if is_dry_run {
         println!("{}", "Dry run summary:".cyan().bold());
     } else {
-        println!("{}", "Moved files:".green().bold());
+        println!("{}", format!("{} files:", result.mode.past()).green().bold());
     }
 
     if result.moved.is_empty() {
         println!("  (none)");
     } else {
-        for (src, dest) in &result.moved {
-            println!("  {} {} {}", src.dimmed(), "→".bright_black(), dest.bold());
+        for file in &result.moved {
+            let companion = match (&file.companion_of, &file.part_of, file.learned) {
+                (Some(_), _, _) => " (sidecar)".bright_black().to_string(),
+                (_, Some(set), _) => format!(" (volume of {})", set).bright_black().to_string(),
+                (_, _, Some(confidence)) => format!(" (learned, {}%)", confidence).bright_black().to_string(),
+                _ => String::new(),
+            };
+            println!(
+                "  {} {} {}{}",
+                file.source.dimmed(),
+                "→".bright_black(),
+                file.destination.bold(),
+                companion
+            );
+            if verbosity() >= Verbosity::Verbose {
+                for (key, value) in &file.metadata {
+                    println!("      {} {}", format!("{}:", key).bright_black(), value);
+                }
+            }
         }
     }
 
@@ -159,8 +866,50 @@ fn print_results(result: &ProcessingResult, is_dry_run: bool) {
         }
     }
 
+    if !result.filtered.is_empty() {
+        println!("\n{}", "Filtered out:".yellow().bold());
+        for file in &result.filtered {
+            println!(
+                "  {} {}",
+                file.path.dimmed(),
+                format!("({})", file.reason).bright_black()
+            );
+        }
+    }
+
+    if !result.ignored.is_empty() {
+        println!("\n{}", "Ignored:".yellow().bold());
+        for file in &result.ignored {
+            println!(
+                "  {} {}",
+                file.path.dimmed(),
+                format!("({})", file.reason).bright_black()
+            );
+        }
+    }
+
+    if !result.incomplete.is_empty() {
+        println!("\n{}", "Incomplete multi-part sets (left in place):".yellow().bold());
+        for set in &result.incomplete {
+            println!(
+                "  {} {}",
+                set.name.dimmed(),
+                format!("({}, {} present)", set.reason, set.volumes.len()).bright_black()
+            );
+        }
+    }
+
+    if !result.failed.is_empty() {
+        println!("\n{}", "Failed:".red().bold());
+        for file in &result.failed {
+            println!("  {}", file.path.bold());
+            println!("    {}", file.error.red());
+        }
+    }
+
     if !result.warnings.is_empty() {
-        println!("\n{}", "Dry-run warnings:".bright_yellow().bold());
+        let heading = if is_dry_run { "Dry-run warnings:" } else { "Warnings:" };
+        println!("\n{}", heading.bright_yellow().bold());
         for warn in &result.warnings {
             println!("  {}", warn.dimmed());
         }
@@ -169,10 +918,18 @@ fn print_results(result: &ProcessingResult, is_dry_run: bool) {
 
 fn print_summary(result: &ProcessingResult, is_dry_run: bool) {
     println!("\nSummary:");
+    if !result.quarantined.is_empty() {
+        let label = if is_dry_run { "Would quarantine:" } else { "Quarantined:" };
+        println!(
+            "  {} {}",
+            label.bright_red().bold(),
+            result.quarantined.len().to_string().bright_red().bold()
+        );
+    }
     if is_dry_run {
         println!(
             "  {} {}",
-            "Would move:".cyan(),
+            format!("Would {}:", result.mode.verb()).cyan(),
             result.moved.len().to_string().bold()
         );
         println!(
@@ -180,60 +937,358 @@ fn print_summary(result: &ProcessingResult, is_dry_run: bool) {
             "Would skip:".cyan(),
             result.skipped.len().to_string().bold()
         );
+        println!(
+            "  {} {}",
+            "Filtered out:".cyan(),
+            result.filtered.len().to_string().bold()
+        );
         println!(
             "  {} {}",
             "Warnings:".yellow(),
             result.warnings.len().to_string().bold()
         );
     } else {
-        println!("  {} {}", "Moved:".green(), result.moved.len().to_string().bold());
+        println!("  {} {}", format!("{}:", result.mode.past()).green(), result.moved.len().to_string().bold());
         println!(
             "  {} {}",
             "Skipped:".yellow(),
             result.skipped.len().to_string().bold()
         );
+        println!(
+            "  {} {}",
+            "Filtered out:".yellow(),
+            result.filtered.len().to_string().bold()
+        );
+    }
+    if !result.ignored.is_empty() {
+        println!(
+            "  {} {}",
+            "Ignored:".yellow(),
+            result.ignored.len().to_string().bold()
+        );
+    }
+    if !result.incomplete.is_empty() {
+        println!(
+            "  {} {}",
+            "Incomplete sets:".yellow(),
+            result.incomplete.len().to_string().bold()
+        );
+    }
+    if !result.failed.is_empty() {
+        println!(
+            "  {} {}",
+            "Failed:".red().bold(),
+            result.failed.len().to_string().red().bold()
+        );
     }
     println!();
 }
 
-fn main() -> Result<()> {
-    print_banner();
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    dry_run: bool,
+    #[serde(flatten)]
+    result: &'a ProcessingResult,
+}
+
+fn print_json(result: &ProcessingResult, is_dry_run: bool) -> Result<()> {
+    let report = JsonReport {
+        dry_run: is_dry_run,
+        result,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Exit status for a finished run: failures outrank skips, which outrank having nothing to do.
+fn exit_status(result: &ProcessingResult) -> u8 {
+    if !result.failed.is_empty() {
+        EXIT_FAILED
+    } else if !result.skipped.is_empty() || !result.incomplete.is_empty() {
+        EXIT_SKIPPED
+    } else if result.moved.is_empty() && result.quarantined.is_empty() {
+        EXIT_NOTHING_TO_DO
+    } else {
+        0
+    }
+}
 
+fn main() -> ExitCode {
+    match run() {
+        Ok(status) => ExitCode::from(status),
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run() -> Result<u8> {
     let args = Args::parse();
+    output::init(args.quiet, args.verbose);
+
+    signatures::init(SignatureDb::load(args.signatures.as_deref())?);
+    match &args.command {
+        Some(Command::Signatures { action }) => return commands::signatures::run(action, signatures::db()).map(|_| 0),
+        Some(Command::Identify { paths }) => return commands::identify::run(paths, &args).map(|_| 0),
+        Some(Command::Stats { dir, top }) => return commands::stats::run(dir, *top, &args).map(|_| 0),
+        Some(Command::History { action }) => return commands::history::run(action.as_ref(), args.json).map(|_| 0),
+        None => {}
+    }
+
+    let chatty = !args.json && verbosity() >= Verbosity::Normal;
+    if chatty {
+        print_banner();
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+    let update_settings = UpdateSettings::resolve(&args, &config)?;
 
-    if !args.no_check_updates {
-        check_for_updates(args.prerelease_channel)?;
+    if args.update {
+        return self_update(&update_settings).map(|_| 0);
+    }
+
+    if update_settings.enabled && chatty {
+        check_for_updates(&update_settings)?;
     }
 
     let cwd = std::env::current_dir().context("cannot get current directory")?;
+    let started = SystemTime::now();
+    let mut result = ProcessingResult { mode: args.mode, ..ProcessingResult::new() };
+    let outcome = sort_files(&cwd, &args, &config, &mut result);
+    let status = exit_status(&result);
+
+    if !args.no_history && (outcome.is_err() || !result.is_empty()) {
+        let error = outcome.as_ref().err().map(|err| format!("{:#}", err));
+        let mut record = RunRecord::new(started, &cwd, args.dry_run, result, error);
+        match record.save() {
+            Ok(()) if chatty && outcome.is_ok() => {
+                let hint = match args.dry_run {
+                    true => format!("Run recorded as {}", record.id),
+                    false => format!("Run recorded as {} (undo with `sortify history undo {}`)", record.id, record.id),
+                };
+                output::info(hint.dimmed());
+            }
+            Ok(()) => {}
+            Err(err) => eprintln!("{} {:#}", "Cannot record run history:".yellow(), err),
+        }
+    }
+    outcome.map(|_| status)
+}
+
+fn sort_files(cwd: &Path, args: &Args, config: &Config, result: &mut ProcessingResult) -> Result<()> {
     let current_exe = std::env::current_exe().ok().and_then(|p| fs::canonicalize(p).ok());
 
-    let entries = collect_files(&cwd)?;
+    let filter = FileFilter::from_args(args);
+    let entries = collect_files(cwd, &filter, args.symlinks, result)?;
+    warn_hard_links(&entries, result);
+    let (entries, volume_sets) = multipart::group(entries);
+    let sidecar_rules = SidecarRules::from_config(&config.sidecars).filter(|_| !args.no_sidecars);
+    let (entries, mut sidecars) = match &sidecar_rules {
+        Some(rules) => sidecar::group(entries, rules),
+        None => (entries, HashMap::new()),
+    };
 
-    if entries.is_empty() {
-        println!("{}", "No files found in current directory.".dimmed());
+    if entries.is_empty() && volume_sets.is_empty() && result.filtered.is_empty() && result.ignored.is_empty() && !args.json {
+        output::info("No files found in current directory.".dimmed());
         return Ok(());
     }
 
-    println!("{}", "\nProcessing files...".bold());
+    if !args.json {
+        output::info("\nProcessing files...".bold());
+    }
 
-    let pb = create_progress_bar(entries.len() as u64);
+    let pb = create_progress_bar((entries.len() + volume_sets.len()) as u64);
     let mut policy = BinaryPolicy::AskEvery;
-    let mut result = ProcessingResult::new();
+    let mut layouts = Layouts::from_args(args).learn(args, cwd)?;
+    if let Some(learned) = &layouts.learned {
+        let (folders, files) = learned.model.size();
+        output::verbose(format!("Learned from {} files in {} folders of {}", files, folders, learned.model.root().display()));
+    }
+
+    for set in &volume_sets {
+        pb.set_message(format!("Processing {}", set.name()));
+        pb.tick();
+        if let Err(err) = process_volume_set(set, cwd, args, result) {
+            result.fail(set.first(), err, args.fail_fast)?;
+        }
+        pb.inc(1);
+    }
 
     for entry in entries {
         let filename = entry.file_name().and_then(|s| s.to_str()).unwrap_or("unknown");
         pb.set_message(format!("Processing {}", filename));
         pb.tick();
 
-        process_file(entry, &cwd, &current_exe, &mut policy, &args, &mut result)?;
+        if args.extract {
+            let extracted = match extract_archive(&entry, cwd, &current_exe, &mut policy, args, &mut layouts, result) {
+                Ok(extracted) => extracted,
+                Err(err) => {
+                    result.fail(&entry, err, args.fail_fast)?;
+                    true
+                }
+            };
+            if extracted {
+                pb.inc(1);
+                continue;
+            }
+        }
+
+        let companions = sidecars.remove(&entry).unwrap_or_default();
+        let placed = match process_file(entry.clone(), cwd, &current_exe, &mut policy, args, &mut layouts, result) {
+            Ok(placed) => placed,
+            Err(err) => {
+                result.fail(&entry, err, args.fail_fast)?;
+                None
+            }
+        };
+        if !companions.is_empty() {
+            move_sidecars(&entry, placed.as_ref(), &companions, cwd, args, result)?;
+        }
         pb.inc(1);
     }
 
     pb.finish_and_clear();
 
-    print_results(&result, args.dry_run);
-    print_summary(&result, args.dry_run);
+    if args.tag && !args.dry_run {
+        tag_sorted(cwd, result);
+    }
+
+    if args.json {
+        return print_json(result, args.dry_run);
+    }
+    if verbosity() == Verbosity::Quiet {
+        for file in &result.failed {
+            eprintln!("{} {}: {}", "Failed:".red().bold(), file.path, file.error);
+        }
+        return Ok(());
+    }
+
+    println!("{}", "Sorting completed".green().bold());
+    println!();
+    print_results(result, args.dry_run);
+    print_summary(result, args.dry_run);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-main-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn args(extra: &[&str]) -> Args {
+        Args::parse_from(["sortify", "--json", "--ext-only", "--no-check-updates"].iter().chain(extra))
+    }
+
+    fn extract_in(dir: &Path, archive: &Path, args: &Args, result: &mut ProcessingResult) -> Result<bool> {
+        let mut layouts = Layouts::from_args(args);
+        extract_archive(archive, dir, &None, &mut BinaryPolicy::AskEvery, args, &mut layouts, result)
+    }
+
+    #[test]
+    fn unreadable_archives_fall_back_to_normal_sorting() {
+        let dir = scratch("extract-fallback");
+        let args = args(&["--extract"]);
+
+        // Vanished before extraction: no staging dir was ever made
+        let mut result = ProcessingResult::new();
+        assert!(!extract_in(&dir, &dir.join("gone.zip"), &args, &mut result).unwrap());
+        assert!(result.warnings[0].starts_with("Cannot extract"));
+
+        let corrupt = dir.join("corrupt.zip");
+        fs::write(&corrupt, b"PK\x03\x04 not really a zip").unwrap();
+        let mut result = ProcessingResult::new();
+        assert!(!extract_in(&dir, &corrupt, &args, &mut result).unwrap());
+        assert!(result.warnings[0].contains("corrupt.zip"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "staging dir left behind");
+
+        // The whole run then sorts the archive as it is
+        let mut result = ProcessingResult::new();
+        sort_files(&dir, &args, &Config::default(), &mut result).unwrap();
+        assert!(result.failed.is_empty());
+        assert_eq!(result.moved.len(), 1);
+        assert!(dir.join("Archives/corrupt.zip").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn fail_records_the_error_unless_failing_fast() {
+        let mut result = ProcessingResult::new();
+        result.fail(Path::new("a.txt"), anyhow::anyhow!("disk on fire"), false).unwrap();
+        assert_eq!((result.failed[0].path.as_str(), result.failed[0].error.as_str()), ("a.txt", "disk on fire"));
+
+        let err = result.fail(Path::new("b.txt"), anyhow::anyhow!("again"), true).unwrap_err();
+        assert_eq!(err.to_string(), "again");
+        assert_eq!(result.failed.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn per_file_errors_do_not_stop_the_run() {
+        let dir = scratch("fail");
+        // A dangling link where the Documents folder should go makes every document fail
+        std::os::unix::fs::symlink(dir.join("nowhere"), dir.join("Documents")).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        fs::write(dir.join("photo.jpg"), "jpg").unwrap();
+
+        let mut result = ProcessingResult::new();
+        sort_files(&dir, &args(&[]), &Config::default(), &mut result).unwrap();
+        assert_eq!(result.failed.len(), 3);
+        assert!(result.failed.iter().all(|f| f.error.contains("Documents")), "{:?}", result.failed[0].error);
+        assert!(dir.join("Pictures/photo.jpg").exists());
+        assert_eq!(exit_status(&result), EXIT_FAILED);
+
+        fs::rename(dir.join("Pictures/photo.jpg"), dir.join("photo.jpg")).unwrap();
+        let mut result = ProcessingResult::new();
+        assert!(sort_files(&dir, &args(&["--fail-fast"]), &Config::default(), &mut result).is_err());
+        assert!(result.failed.is_empty());
+        assert!(dir.join("a.txt").exists() && dir.join("b.txt").exists() && dir.join("c.txt").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn outcomes_map_to_exit_codes() {
+        let moved = || MovedFile {
+            source: "a.txt".to_string(),
+            destination: "Documents".to_string(),
+            category: "Documents".to_string(),
+            metadata: Metadata::new(),
+            companion_of: None,
+            part_of: None,
+            learned: None,
+        };
+
+        let mut result = ProcessingResult::new();
+        assert_eq!(exit_status(&result), EXIT_NOTHING_TO_DO);
+        result.filtered.push(FilteredFile { path: "old.log".to_string(), reason: "too old".to_string() });
+        assert_eq!(exit_status(&result), EXIT_NOTHING_TO_DO);
+
+        result.moved.push(moved());
+        assert_eq!(exit_status(&result), 0);
+
+        let mut quarantined = ProcessingResult::new();
+        quarantined.quarantined.push(QuarantinedFile {
+            source: "x.pdf.exe".to_string(),
+            destination: "Quarantine".to_string(),
+            reasons: vec![],
+        });
+        assert_eq!(exit_status(&quarantined), 0);
+
+        result.skipped.push("tool".to_string());
+        assert_eq!(exit_status(&result), EXIT_SKIPPED);
+        let mut incomplete = ProcessingResult::new();
+        incomplete.incomplete.push(IncompleteSet { name: "a.part1.rar".to_string(), volumes: vec![], reason: "gap".to_string() });
+        assert_eq!(exit_status(&incomplete), EXIT_SKIPPED);
+
+        result.failed.push(FailedFile { path: "b.txt".to_string(), error: "denied".to_string() });
+        assert_eq!(exit_status(&result), EXIT_FAILED);
+    }
+}
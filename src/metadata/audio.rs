@@ -0,0 +1,471 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::metadata::Metadata;
+use crate::metadata::bmff::{be, find_box, read_box_header};
+
+const MAX_FRAME: u64 = 64 * 1024;
+const MAX_OGG_SCAN: usize = 512 * 1024;
+
+/// Reads ID3v2/ID3v1, FLAC/OGG Vorbis comments or MP4 `ilst` atoms, whichever the file has.
+pub fn read_tags(path: &Path) -> Result<Metadata> {
+    let mut f = File::open(path)?;
+    let mut magic = [0u8; 12];
+    let n = f.read(&mut magic)?;
+    let magic = &magic[..n];
+    f.seek(SeekFrom::Start(0))?;
+
+    let mut meta = Metadata::new();
+    if magic.starts_with(b"ID3") {
+        read_id3v2(&mut f, &mut meta)?;
+    } else if magic.starts_with(b"fLaC") {
+        read_flac(&mut f, &mut meta)?;
+    } else if magic.starts_with(b"OggS") {
+        read_ogg(&mut f, &mut meta)?;
+    } else if magic.len() >= 8 && &magic[4..8] == b"ftyp" {
+        read_mp4(&mut f, &mut meta)?;
+    }
+
+    if !meta.contains_key("title") || !meta.contains_key("artist") {
+        read_id3v1(&mut f, &mut meta)?;
+    }
+
+    Ok(meta)
+}
+
+fn insert(meta: &mut Metadata, key: &str, value: &str) {
+    let value = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    if !value.is_empty() && !meta.contains_key(key) {
+        meta.insert(key.to_string(), value.to_string());
+    }
+}
+
+/// "3/12" -> "3"
+fn insert_number(meta: &mut Metadata, key: &str, value: &str) {
+    let number = value.split('/').next().unwrap_or("").trim();
+    if let Ok(n) = number.parse::<u32>()
+        && n > 0
+    {
+        insert(meta, key, &n.to_string());
+    }
+}
+
+fn insert_year(meta: &mut Metadata, value: &str) {
+    let year: String = value.trim().chars().take(4).collect();
+    if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) {
+        insert(meta, "year", &year);
+    }
+}
+
+fn syncsafe(b: &[u8]) -> u64 {
+    b.iter().fold(0u64, |acc, &x| (acc << 7) | (x & 0x7F) as u64)
+}
+
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| if big_endian { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+        .take_while(|&u| u != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn decode_id3_text(data: &[u8]) -> String {
+    let Some((&enc, body)) = data.split_first() else {
+        return String::new();
+    };
+    match enc {
+        0 => body.iter().take_while(|&&b| b != 0).map(|&b| b as char).collect(),
+        1 => match body {
+            [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, false),
+            [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, true),
+            _ => decode_utf16(body, false),
+        },
+        2 => decode_utf16(body, true),
+        _ => {
+            let end = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+            String::from_utf8_lossy(&body[..end]).into_owned()
+        }
+    }
+}
+
+fn id3_key(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "TPE1" | "TP1" => "artist",
+        "TPE2" | "TP2" => "albumartist",
+        "TALB" | "TAL" => "album",
+        "TIT2" | "TT2" => "title",
+        "TRCK" | "TRK" => "track",
+        "TPOS" | "TPA" => "disc",
+        "TYER" | "TDRC" | "TYE" | "TORY" => "year",
+        "TCON" | "TCO" => "genre",
+        _ => return None,
+    })
+}
+
+fn read_id3v2(f: &mut File, meta: &mut Metadata) -> Result<()> {
+    let mut header = [0u8; 10];
+    f.read_exact(&mut header)?;
+    let version = header[3];
+    let flags = header[5];
+    let tag_end = 10 + syncsafe(&header[6..10]);
+
+    if flags & 0x40 != 0 && version >= 3 {
+        let mut ext = [0u8; 4];
+        f.read_exact(&mut ext)?;
+        let ext_size = if version == 4 { syncsafe(&ext) } else { be(&ext) + 4 };
+        f.seek(SeekFrom::Start(10 + ext_size))?;
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut frame = [0u8; 10];
+
+    while f.stream_position()? + header_len as u64 <= tag_end {
+        f.read_exact(&mut frame[..header_len])?;
+        if frame[0] == 0 {
+            break;
+        }
+
+        let id = String::from_utf8_lossy(&frame[..id_len]).into_owned();
+        let size = match version {
+            2 => be(&frame[3..6]),
+            4 => syncsafe(&frame[4..8]),
+            _ => be(&frame[4..8]),
+        };
+
+        match id3_key(&id) {
+            Some(key) if size <= MAX_FRAME => {
+                let mut data = vec![0u8; size as usize];
+                f.read_exact(&mut data)?;
+                let text = decode_id3_text(&data);
+                match key {
+                    "track" | "disc" => insert_number(meta, key, &text),
+                    "year" => insert_year(meta, &text),
+                    _ => insert(meta, key, &text),
+                }
+            }
+            _ => {
+                f.seek(SeekFrom::Current(size as i64))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+const ID3V1_GENRES: &[&str] = &[
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop", "Jazz",
+    "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap", "Reggae", "Rock", "Techno",
+    "Industrial", "Alternative", "Ska", "Death Metal", "Pranks", "Soundtrack", "Euro-Techno",
+    "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance", "Classical", "Instrumental",
+    "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise", "Alternative Rock", "Bass", "Soul",
+    "Punk", "Space", "Meditative", "Instrumental Pop", "Instrumental Rock", "Ethnic", "Gothic",
+    "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream",
+    "Southern Rock", "Comedy", "Cult", "Gangsta", "Top 40", "Christian Rap", "Pop/Funk",
+    "Jungle", "Native American", "Cabaret", "New Wave", "Psychedelic", "Rave", "Showtunes",
+    "Trailer", "Lo-Fi", "Tribal", "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical",
+    "Rock & Roll", "Hard Rock",
+];
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().take_while(|&&b| b != 0).map(|&b| b as char).collect()
+}
+
+fn read_id3v1(f: &mut File, meta: &mut Metadata) -> Result<()> {
+    if f.metadata()?.len() < 128 {
+        return Ok(());
+    }
+
+    let mut tag = [0u8; 128];
+    f.seek(SeekFrom::End(-128))?;
+    f.read_exact(&mut tag)?;
+    if &tag[..3] != b"TAG" {
+        return Ok(());
+    }
+
+    insert(meta, "title", &latin1(&tag[3..33]));
+    insert(meta, "artist", &latin1(&tag[33..63]));
+    insert(meta, "album", &latin1(&tag[63..93]));
+    insert_year(meta, &latin1(&tag[93..97]));
+    if tag[125] == 0 && tag[126] != 0 {
+        insert_number(meta, "track", &tag[126].to_string());
+    }
+    if let Some(genre) = ID3V1_GENRES.get(tag[127] as usize) {
+        insert(meta, "genre", genre);
+    }
+
+    Ok(())
+}
+
+fn apply_vorbis_comments(data: &[u8], meta: &mut Metadata) {
+    let mut pos = 0usize;
+    let next_u32 = |pos: &mut usize| -> Option<usize> {
+        let b = data.get(*pos..*pos + 4)?;
+        *pos += 4;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+
+    let Some(vendor_len) = next_u32(&mut pos) else { return };
+    pos += vendor_len;
+    let Some(count) = next_u32(&mut pos) else { return };
+
+    for _ in 0..count.min(1024) {
+        let Some(len) = next_u32(&mut pos) else { return };
+        let Some(entry) = data.get(pos..pos + len) else { return };
+        pos += len;
+
+        let entry = String::from_utf8_lossy(entry);
+        let Some((key, value)) = entry.split_once('=') else { continue };
+        match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => insert(meta, "artist", value),
+            "ALBUMARTIST" | "ALBUM ARTIST" => insert(meta, "albumartist", value),
+            "ALBUM" => insert(meta, "album", value),
+            "TITLE" => insert(meta, "title", value),
+            "TRACKNUMBER" => insert_number(meta, "track", value),
+            "DISCNUMBER" => insert_number(meta, "disc", value),
+            "DATE" | "YEAR" => insert_year(meta, value),
+            "GENRE" => insert(meta, "genre", value),
+            _ => {}
+        }
+    }
+}
+
+fn read_flac(f: &mut File, meta: &mut Metadata) -> Result<()> {
+    f.seek(SeekFrom::Start(4))?;
+    let mut header = [0u8; 4];
+
+    loop {
+        f.read_exact(&mut header)?;
+        let last = header[0] & 0x80 != 0;
+        let block_type = header[0] & 0x7F;
+        let len = be(&header[1..4]);
+
+        if block_type == 4 && len <= MAX_FRAME * 16 {
+            let mut data = vec![0u8; len as usize];
+            f.read_exact(&mut data)?;
+            apply_vorbis_comments(&data, meta);
+            return Ok(());
+        }
+        if last {
+            return Ok(());
+        }
+        f.seek(SeekFrom::Current(len as i64))?;
+    }
+}
+
+/// Reassembles the second logical packet (the comment header) of an Ogg stream.
+fn read_ogg(f: &mut File, meta: &mut Metadata) -> Result<()> {
+    let mut buf = Vec::new();
+    f.take(MAX_OGG_SCAN as u64).read_to_end(&mut buf)?;
+
+    let mut pos = 0usize;
+    let mut packet_index = 0;
+    let mut packet = Vec::new();
+
+    while pos + 27 <= buf.len() && &buf[pos..pos + 4] == b"OggS" {
+        let segments = buf[pos + 26] as usize;
+        let lacing_start = pos + 27;
+        let Some(lacing) = buf.get(lacing_start..lacing_start + segments) else { break };
+        let mut data_pos = lacing_start + segments;
+
+        for &lace in lacing {
+            let Some(seg) = buf.get(data_pos..data_pos + lace as usize) else { return Ok(()) };
+            data_pos += lace as usize;
+            if packet_index == 1 {
+                packet.extend_from_slice(seg);
+            }
+            if lace < 255 {
+                if packet_index == 1 {
+                    let body = packet
+                        .strip_prefix(b"\x03vorbis")
+                        .or_else(|| packet.strip_prefix(b"OpusTags"))
+                        .unwrap_or(&[]);
+                    apply_vorbis_comments(body, meta);
+                    return Ok(());
+                }
+                packet_index += 1;
+            }
+        }
+        pos = data_pos;
+    }
+
+    Ok(())
+}
+
+fn read_mp4(f: &mut File, meta: &mut Metadata) -> Result<()> {
+    let len = f.metadata()?.len();
+    let Some((start, end)) = find_box(f, 0, len, &[b"moov", b"udta", b"meta", b"ilst"])? else {
+        return Ok(());
+    };
+
+    f.seek(SeekFrom::Start(start))?;
+    while f.stream_position()? < end {
+        let Some((_, kind, item_end)) = read_box_header(f)? else { break };
+        let key = match &kind {
+            b"\xA9ART" => "artist",
+            b"aART" => "albumartist",
+            b"\xA9alb" => "album",
+            b"\xA9nam" => "title",
+            b"\xA9day" => "year",
+            b"\xA9gen" => "genre",
+            b"trkn" => "track",
+            b"disk" => "disc",
+            _ => "",
+        };
+
+        let content_len = item_end.saturating_sub(f.stream_position()?);
+        if !key.is_empty() && content_len <= MAX_FRAME {
+            let mut item = vec![0u8; content_len as usize];
+            f.read_exact(&mut item)?;
+            // item = data box: size(4) "data"(4) type(4) locale(4) payload
+            if item.len() >= 16 && &item[4..8] == b"data" {
+                let payload = &item[16..];
+                match key {
+                    "track" | "disc" if payload.len() >= 4 => {
+                        insert_number(meta, key, &be(&payload[2..4]).to_string())
+                    }
+                    "year" => insert_year(meta, &String::from_utf8_lossy(payload)),
+                    _ => insert(meta, key, &String::from_utf8_lossy(payload)),
+                }
+            }
+        }
+        f.seek(SeekFrom::Start(item_end))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sortify-audio-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn read(name: &str, bytes: &[u8]) -> Result<Metadata> {
+        let path = write(name, bytes);
+        let tags = read_tags(&path);
+        fs::remove_file(&path).ok();
+        tags
+    }
+
+    fn syncsafe_bytes(n: usize) -> [u8; 4] {
+        [(n >> 21) as u8 & 0x7F, (n >> 14) as u8 & 0x7F, (n >> 7) as u8 & 0x7F, n as u8 & 0x7F]
+    }
+
+    fn id3v2(version: u8, frames: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, data) in frames {
+            body.extend(id.as_bytes());
+            match version {
+                2 => body.extend(&(data.len() as u32).to_be_bytes()[1..]),
+                4 => body.extend(syncsafe_bytes(data.len())),
+                _ => body.extend((data.len() as u32).to_be_bytes()),
+            }
+            if version > 2 {
+                body.extend([0, 0]);
+            }
+            body.extend(data);
+        }
+        body.extend([0; 16]);
+
+        let mut tag = b"ID3".to_vec();
+        tag.extend([version, 0, 0]);
+        tag.extend(syncsafe_bytes(body.len()));
+        tag.extend(body);
+        tag
+    }
+
+    fn latin1_frame(text: &str) -> Vec<u8> {
+        let mut data = vec![0];
+        data.extend(text.as_bytes());
+        data
+    }
+
+    fn get<'a>(meta: &'a Metadata, key: &str) -> Option<&'a str> {
+        meta.get(key).map(String::as_str)
+    }
+
+    #[test]
+    fn id3v23_frames_in_every_encoding() {
+        let mut utf16 = vec![1, 0xFF, 0xFE];
+        utf16.extend("Tïtle".encode_utf16().flat_map(u16::to_le_bytes));
+        let mut utf16be = vec![2];
+        utf16be.extend("Genre".encode_utf16().flat_map(u16::to_be_bytes));
+        let mut utf8 = vec![3];
+        utf8.extend("Älbum\0".as_bytes());
+
+        let tag = id3v2(3, &[
+            ("TPE1", latin1_frame("Artist")),
+            ("APIC", vec![0; 4096]),
+            ("TIT2", utf16),
+            ("TALB", utf8),
+            ("TCON", utf16be),
+            ("TRCK", latin1_frame("3/12")),
+            ("TYER", latin1_frame("2019")),
+        ]);
+        let meta = read("v23.mp3", &tag).unwrap();
+        assert_eq!(get(&meta, "artist"), Some("Artist"));
+        assert_eq!(get(&meta, "title"), Some("Tïtle"));
+        assert_eq!(get(&meta, "album"), Some("Älbum"));
+        assert_eq!(get(&meta, "genre"), Some("Genre"));
+        assert_eq!(get(&meta, "track"), Some("3"));
+        assert_eq!(get(&meta, "year"), Some("2019"));
+    }
+
+    #[test]
+    fn id3v24_sizes_are_syncsafe_and_id3v22_ids_are_short() {
+        let long = "x".repeat(300);
+        let meta = read("v24.mp3", &id3v2(4, &[("TIT2", latin1_frame(&long)), ("TPE1", latin1_frame("A"))])).unwrap();
+        assert_eq!(get(&meta, "title"), Some(long.as_str()));
+        assert_eq!(get(&meta, "artist"), Some("A"));
+
+        let meta = read("v22.mp3", &id3v2(2, &[("TT2", latin1_frame("Old")), ("TRK", latin1_frame("0"))])).unwrap();
+        assert_eq!(get(&meta, "title"), Some("Old"));
+        assert_eq!(get(&meta, "track"), None);
+    }
+
+    #[test]
+    fn id3v1_fills_what_id3v2_lacks() {
+        let mut file = id3v2(3, &[("TIT2", latin1_frame("From v2"))]);
+        file.extend([0xFF; 64]);
+        let mut v1 = [0u8; 128];
+        v1[..3].copy_from_slice(b"TAG");
+        v1[3..10].copy_from_slice(b"From v1");
+        v1[33..39].copy_from_slice(b"Singer");
+        v1[93..97].copy_from_slice(b"1999");
+        v1[126] = 7;
+        v1[127] = 17;
+        file.extend(v1);
+
+        let meta = read("v1.mp3", &file).unwrap();
+        assert_eq!(get(&meta, "title"), Some("From v2"));
+        assert_eq!(get(&meta, "artist"), Some("Singer"));
+        assert_eq!(get(&meta, "year"), Some("1999"));
+        assert_eq!(get(&meta, "track"), Some("7"));
+        assert_eq!(get(&meta, "genre"), Some("Rock"));
+    }
+
+    #[test]
+    fn broken_tags_never_panic() {
+        let tag = id3v2(3, &[("TPE1", latin1_frame("Artist")), ("TIT2", latin1_frame("Title"))]);
+        for len in 0..tag.len() {
+            let _ = read("cut.mp3", &tag[..len]);
+        }
+
+        let mut huge = tag.clone();
+        huge[14..18].copy_from_slice(&u32::MAX.to_be_bytes());
+        let _ = read("huge.mp3", &huge);
+
+        let mut ext = tag;
+        ext[5] = 0x40;
+        let _ = read("ext.mp3", &ext);
+    }
+}
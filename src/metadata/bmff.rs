@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Big-endian unsigned integer of up to 8 bytes.
+pub fn be(b: &[u8]) -> u64 {
+    b.iter().fold(0u64, |acc, &x| (acc << 8) | x as u64)
+}
+
+/// Reads a box header at the current position: `(start, type, end)`.
+pub fn read_box_header(f: &mut File) -> Result<Option<(u64, [u8; 4], u64)>> {
+    let start = f.stream_position()?;
+    let mut h = [0u8; 8];
+    if f.read(&mut h)? < 8 {
+        return Ok(None);
+    }
+    let mut size = be(&h[..4]);
+    let mut header_len = 8;
+    if size == 1 {
+        let mut large = [0u8; 8];
+        f.read_exact(&mut large)?;
+        size = be(&large);
+        header_len = 16;
+    } else if size == 0 {
+        size = f.metadata()?.len().saturating_sub(start);
+    }
+    if size < header_len {
+        return Ok(None);
+    }
+    // A 64-bit largesize can point anywhere, including past the end of the address space
+    match start.checked_add(size) {
+        Some(end) if end > start => Ok(Some((start, [h[4], h[5], h[6], h[7]], end))),
+        _ => Ok(None),
+    }
+}
+
+/// Finds a child box by type path, e.g. `moov/udta/meta/ilst`, returning its content range.
+pub fn find_box(f: &mut File, start: u64, end: u64, path: &[&[u8; 4]]) -> Result<Option<(u64, u64)>> {
+    let Some((first, rest)) = path.split_first() else {
+        return Ok(Some((start, end)));
+    };
+
+    f.seek(SeekFrom::Start(start))?;
+    while f.stream_position()? < end {
+        let Some((_, kind, box_end)) = read_box_header(f)? else { break };
+        if &kind == *first {
+            let mut content = f.stream_position()?;
+            if &kind == b"meta" {
+                content += 4; // full box: version + flags
+            }
+            return find_box(f, content, box_end.min(end), rest);
+        }
+        f.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(None)
+}
+
+/// Direct children of a box as `(type, content_start, end)`.
+pub fn children(f: &mut File, start: u64, end: u64) -> Result<Vec<([u8; 4], u64, u64)>> {
+    let mut out = Vec::new();
+    f.seek(SeekFrom::Start(start))?;
+    while f.stream_position()? < end && out.len() < 1024 {
+        let Some((_, kind, box_end)) = read_box_header(f)? else { break };
+        out.push((kind, f.stream_position()?, box_end.min(end)));
+        f.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn file_with(name: &str, bytes: &[u8]) -> File {
+        let path = std::env::temp_dir().join(format!("sortify-bmff-{}-{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(bytes).unwrap();
+        let f = File::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        f
+    }
+
+    #[test]
+    fn reads_plain_and_large_headers() {
+        let mut bytes = vec![0, 0, 0, 12];
+        bytes.extend(b"ftypisom");
+        bytes.extend([0, 0, 0, 1]);
+        bytes.extend(b"free");
+        bytes.extend(20u64.to_be_bytes());
+        bytes.extend([0; 4]);
+        let mut f = file_with("ok", &bytes);
+
+        assert_eq!(read_box_header(&mut f).unwrap(), Some((0, *b"ftyp", 12)));
+        f.seek(SeekFrom::Start(12)).unwrap();
+        assert_eq!(read_box_header(&mut f).unwrap(), Some((12, *b"free", 32)));
+    }
+
+    #[test]
+    fn rejects_boxes_that_overflow() {
+        let mut bytes = vec![0; 8];
+        bytes.extend([0, 0, 0, 1]);
+        bytes.extend(b"moov");
+        bytes.extend(u64::MAX.to_be_bytes());
+        let mut f = file_with("overflow", &bytes);
+        f.seek(SeekFrom::Start(8)).unwrap();
+        assert_eq!(read_box_header(&mut f).unwrap(), None);
+
+        let mut short = vec![0, 0, 0, 4];
+        short.extend(b"moov");
+        assert_eq!(read_box_header(&mut file_with("short", &short)).unwrap(), None);
+        assert_eq!(find_box(&mut file_with("short2", &short), 0, 8, &[b"moov"]).unwrap(), None);
+    }
+}
@@ -0,0 +1,522 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::metadata::Metadata;
+
+/// PE files are scanned this far for installer framework markers.
+const MAX_PE_SCAN: u64 = 4 * 1024 * 1024;
+const HEADER_LEN: u64 = 4096;
+
+const INSTALLER_MARKERS: &[&[u8]] = &[
+    b"Nullsoft Install System",
+    b"NullsoftInst",
+    b"Inno Setup",
+    b"InstallShield",
+    b"WiX Toolset",
+    b"7zS.sfx",
+    b"Squirrel",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecKind {
+    Program,
+    Library,
+    Installer,
+    Package,
+    Script,
+}
+
+impl ExecKind {
+    fn name(self) -> &'static str {
+        match self {
+            ExecKind::Program => "program",
+            ExecKind::Library => "library",
+            ExecKind::Installer => "installer",
+            ExecKind::Package => "package",
+            ExecKind::Script => "script",
+        }
+    }
+
+    /// Sub-folder used by the default exec layout; plain programs get none.
+    fn group(self) -> Option<&'static str> {
+        match self {
+            ExecKind::Program => None,
+            ExecKind::Library => Some("Libraries"),
+            ExecKind::Installer => Some("Installers"),
+            ExecKind::Package => Some("Packages"),
+            ExecKind::Script => Some("Scripts"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecInfo {
+    /// `ELF`, `PE`, `Mach-O`, `AppImage`, `wasm`, `deb`, `rpm`, `MSI` or `script`
+    pub format: &'static str,
+    pub platform: &'static str,
+    /// `x86_64`, `aarch64`, ... or `x86_64+arm64` for universal Mach-O binaries
+    pub arch: Option<String>,
+    pub bits: Option<u8>,
+    pub kind: ExecKind,
+    pub interpreter: Option<String>,
+}
+
+impl ExecInfo {
+    fn new(format: &'static str, platform: &'static str, kind: ExecKind) -> Self {
+        Self { format, platform, arch: None, bits: None, kind, interpreter: None }
+    }
+}
+
+impl fmt::Display for ExecInfo {
+    /// e.g. `ELF 64-bit x86_64 program for Linux`, `script (python3) for Unix`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format)?;
+        if let Some(bits) = self.bits {
+            write!(f, " {}-bit", bits)?;
+        }
+        if let Some(arch) = &self.arch {
+            write!(f, " {}", arch)?;
+        }
+        if self.kind.name() != self.format {
+            write!(f, " {}", self.kind.name())?;
+        }
+        if let Some(interpreter) = &self.interpreter {
+            write!(f, " ({})", interpreter)?;
+        }
+        write!(f, " for {}", self.platform)
+    }
+}
+
+fn u16_at(b: &[u8], at: usize, little: bool) -> Option<u16> {
+    let b: [u8; 2] = b.get(at..at + 2)?.try_into().ok()?;
+    Some(if little { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+}
+
+fn u32_at(b: &[u8], at: usize, little: bool) -> Option<u32> {
+    let b: [u8; 4] = b.get(at..at + 4)?.try_into().ok()?;
+    Some(if little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+}
+
+fn elf(buf: &[u8], path: &Path) -> Option<ExecInfo> {
+    let bits = match buf.get(4)? {
+        1 => 32,
+        2 => 64,
+        _ => return None,
+    };
+    let little = *buf.get(5)? == 1;
+    let e_type = u16_at(buf, 16, little)?;
+    let machine = u16_at(buf, 18, little)?;
+
+    let arch = match (machine, bits) {
+        (0x03, _) => "x86",
+        (0x3E, _) => "x86_64",
+        (0x28, _) => "arm",
+        (0xB7, _) => "aarch64",
+        (0xF3, 64) => "riscv64",
+        (0xF3, _) => "riscv32",
+        (0x08, _) => "mips",
+        (0x14, _) => "powerpc",
+        (0x15, _) => "powerpc64",
+        (0x16, _) => "s390x",
+        (0x102, _) => "loongarch64",
+        _ => "unknown",
+    };
+    let platform = match buf.get(7)? {
+        9 => "FreeBSD",
+        12 => "OpenBSD",
+        _ => "Linux",
+    };
+
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    let is_shared_lib = name.ends_with(".so") || name.contains(".so.");
+    let (format, kind) = match (buf.get(8..11), e_type) {
+        (Some(b"AI\x01") | Some(b"AI\x02"), _) => ("AppImage", ExecKind::Program),
+        (_, 3) if is_shared_lib => ("ELF", ExecKind::Library),
+        _ => ("ELF", ExecKind::Program),
+    };
+
+    let mut info = ExecInfo::new(format, platform, kind);
+    info.arch = Some(arch.to_string());
+    info.bits = Some(bits);
+    Some(info)
+}
+
+fn pe(buf: &[u8], path: &Path) -> Option<ExecInfo> {
+    let pe_at = u32_at(buf, 0x3C, true)? as usize;
+    if buf.get(pe_at..pe_at + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16_at(buf, pe_at + 4, true)?;
+    let characteristics = u16_at(buf, pe_at + 22, true)?;
+    let bits = match u16_at(buf, pe_at + 24, true)? {
+        0x20B => 64,
+        _ => 32,
+    };
+    let arch = match machine {
+        0x014C => "x86",
+        0x8664 => "x86_64",
+        0xAA64 => "arm64",
+        0x01C0 | 0x01C4 => "arm",
+        0x0200 => "ia64",
+        _ => "unknown",
+    };
+
+    let kind = if characteristics & 0x2000 != 0 {
+        ExecKind::Library
+    } else if is_installer(path) {
+        ExecKind::Installer
+    } else {
+        ExecKind::Program
+    };
+
+    let mut info = ExecInfo::new("PE", "Windows", kind);
+    if kind != ExecKind::Installer {
+        info.arch = Some(arch.to_string());
+    }
+    info.bits = Some(bits);
+    Some(info)
+}
+
+/// Words of a file stem, split at punctuation and lower-to-upper case changes:
+/// `VSCodeUserSetup-x64` gives `vscode`, `user`, `setup`, `x64`.
+fn name_words(stem: &str) -> Vec<String> {
+    let mut words = vec![String::new()];
+    let mut prev_lower = false;
+    for c in stem.chars() {
+        if !c.is_alphanumeric() {
+            words.push(String::new());
+        } else if c.is_uppercase() && prev_lower {
+            words.push(c.to_ascii_lowercase().to_string());
+        } else if let Some(word) = words.last_mut() {
+            word.push(c.to_ascii_lowercase());
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    words.retain(|w| !w.is_empty());
+    words
+}
+
+/// Installer frameworks leave their name in the stub; a file name with `setup` or
+/// `install` as a whole word counts too, so `uninstall.exe` does not.
+fn is_installer(path: &Path) -> bool {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if name_words(&stem).iter().any(|w| matches!(w.as_str(), "setup" | "install" | "installer")) {
+        return true;
+    }
+
+    let mut buf = Vec::new();
+    let read = File::open(path).and_then(|f| f.take(MAX_PE_SCAN).read_to_end(&mut buf));
+    read.is_ok() && INSTALLER_MARKERS.iter().any(|m| buf.windows(m.len()).any(|w| w == *m))
+}
+
+fn macho_arch(cputype: u32) -> &'static str {
+    match cputype {
+        7 => "x86",
+        0x0100_0007 => "x86_64",
+        12 => "arm",
+        0x0100_000C => "arm64",
+        0x0200_000C => "arm64_32",
+        18 => "powerpc",
+        0x0100_0012 => "powerpc64",
+        _ => "unknown",
+    }
+}
+
+fn macho(buf: &[u8]) -> Option<ExecInfo> {
+    let magic = u32_at(buf, 0, false)?;
+    let (little, bits) = match magic {
+        0xFEED_FACE => (false, 32),
+        0xFEED_FACF => (false, 64),
+        0xCEFA_EDFE => (true, 32),
+        0xCFFA_EDFE => (true, 64),
+        0xCAFE_BABE => return macho_fat(buf),
+        _ => return None,
+    };
+    let cputype = u32_at(buf, 4, little)?;
+    let kind = match u32_at(buf, 12, little)? {
+        6 | 8 => ExecKind::Library,
+        _ => ExecKind::Program,
+    };
+    let mut info = ExecInfo::new("Mach-O", "macOS", kind);
+    info.arch = Some(macho_arch(cputype).to_string());
+    info.bits = Some(bits);
+    Some(info)
+}
+
+/// Universal binary: a big-endian list of `fat_arch` records. Java class files share the
+/// magic, but their version field reads as a huge architecture count.
+fn macho_fat(buf: &[u8]) -> Option<ExecInfo> {
+    let count = u32_at(buf, 4, false)? as usize;
+    if count == 0 || count > 20 {
+        return None;
+    }
+    let archs: Vec<&str> = (0..count)
+        .filter_map(|i| u32_at(buf, 8 + i * 20, false))
+        .map(macho_arch)
+        .collect();
+    let mut info = ExecInfo::new("Mach-O", "macOS", ExecKind::Program);
+    info.arch = Some(archs.join("+"));
+    Some(info)
+}
+
+/// Interpreter named by a `#!` line; `/usr/bin/env python3` gives `python3`.
+fn shebang(buf: &[u8]) -> Option<String> {
+    let line = buf.strip_prefix(b"#!")?;
+    let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?.to_string();
+    match program.as_str() {
+        "env" => words.find(|w| !w.starts_with('-')).map(str::to_string),
+        _ => Some(program),
+    }
+}
+
+#[cfg(unix)]
+fn has_exec_bit(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn has_exec_bit(_path: &Path) -> bool {
+    false
+}
+
+/// Identifies executables, packages and installers from their headers, and scripts from a
+/// shebang plus the exec bit.
+pub fn inspect(path: &Path) -> Option<ExecInfo> {
+    let mut buf = Vec::new();
+    File::open(path).ok()?.take(HEADER_LEN).read_to_end(&mut buf).ok()?;
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if buf.starts_with(b"\x7FELF") {
+        elf(&buf, path)
+    } else if buf.starts_with(b"MZ") {
+        pe(&buf, path)
+    } else if buf.starts_with(b"\0asm") {
+        let mut info = ExecInfo::new("wasm", "Any", ExecKind::Program);
+        info.arch = Some("wasm32".to_string());
+        Some(info)
+    } else if buf.starts_with(b"!<arch>\ndebian-binary") {
+        Some(ExecInfo::new("deb", "Linux", ExecKind::Package))
+    } else if buf.starts_with(b"\xED\xAB\xEE\xDB") {
+        Some(ExecInfo::new("rpm", "Linux", ExecKind::Package))
+    } else if buf.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") && ext == "msi" {
+        Some(ExecInfo::new("MSI", "Windows", ExecKind::Installer))
+    } else if let Some(info) = macho(&buf) {
+        Some(info)
+    } else if buf.starts_with(b"#!") && has_exec_bit(path) {
+        let mut info = ExecInfo::new("script", "Unix", ExecKind::Script);
+        info.interpreter = shebang(&buf);
+        Some(info)
+    } else {
+        None
+    }
+}
+
+/// Layout keys: `platform`, `arch`, `bits`, `exec_format`, `exec_kind`, `exec_group`, `interpreter`.
+pub fn describe(info: &ExecInfo, meta: &mut Metadata) {
+    meta.insert("platform".to_string(), info.platform.to_string());
+    meta.insert("exec_format".to_string(), info.format.to_string());
+    meta.insert("exec_kind".to_string(), info.kind.name().to_string());
+    if let Some(arch) = &info.arch {
+        meta.insert("arch".to_string(), arch.clone());
+    }
+    if let Some(bits) = info.bits {
+        meta.insert("bits".to_string(), bits.to_string());
+    }
+    if let Some(group) = info.kind.group() {
+        meta.insert("exec_group".to_string(), group.to_string());
+    }
+    if let Some(interpreter) = &info.interpreter {
+        meta.insert("interpreter".to_string(), interpreter.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-exec-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn put(buf: &mut [u8], at: usize, bytes: &[u8]) {
+        buf[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn elf(class: u8, little: bool, osabi: u8, e_type: u16, machine: u16) -> Vec<u8> {
+        let mut buf = vec![0u8; 64];
+        put(&mut buf, 0, b"\x7FELF");
+        buf[4] = class;
+        buf[5] = if little { 1 } else { 2 };
+        buf[7] = osabi;
+        let half = |v: u16| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+        put(&mut buf, 16, &half(e_type));
+        put(&mut buf, 18, &half(machine));
+        buf
+    }
+
+    fn pe(machine: u16, characteristics: u16, optional_magic: u16) -> Vec<u8> {
+        let mut buf = vec![0u8; 0x100];
+        put(&mut buf, 0, b"MZ");
+        put(&mut buf, 0x3C, &0x80u32.to_le_bytes());
+        put(&mut buf, 0x80, b"PE\0\0");
+        put(&mut buf, 0x84, &machine.to_le_bytes());
+        put(&mut buf, 0x96, &characteristics.to_le_bytes());
+        put(&mut buf, 0x98, &optional_magic.to_le_bytes());
+        buf
+    }
+
+    fn macho(cputype: u32, filetype: u32) -> Vec<u8> {
+        let mut buf = vec![0u8; 32];
+        put(&mut buf, 0, &0xFEED_FACFu32.to_le_bytes());
+        put(&mut buf, 4, &cputype.to_le_bytes());
+        put(&mut buf, 12, &filetype.to_le_bytes());
+        buf
+    }
+
+    fn fat(cputypes: &[u32]) -> Vec<u8> {
+        let mut buf = 0xCAFE_BABEu32.to_be_bytes().to_vec();
+        buf.extend((cputypes.len() as u32).to_be_bytes());
+        for cputype in cputypes {
+            let mut arch = [0u8; 20];
+            put(&mut arch, 0, &cputype.to_be_bytes());
+            buf.extend(arch);
+        }
+        buf
+    }
+
+    fn inspect_bytes(dir: &Path, name: &str, bytes: &[u8]) -> Option<ExecInfo> {
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        inspect(&path)
+    }
+
+    fn described(dir: &Path, name: &str, bytes: &[u8]) -> String {
+        inspect_bytes(dir, name, bytes).map(|info| info.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn elf_headers_give_class_machine_and_platform() {
+        let dir = scratch("elf");
+        assert_eq!(described(&dir, "tool", &elf(2, true, 0, 2, 0x3E)), "ELF 64-bit x86_64 program for Linux");
+        assert_eq!(described(&dir, "libz.so.1", &elf(2, true, 0, 3, 0xB7)), "ELF 64-bit aarch64 library for Linux");
+        // Position-independent executables are ET_DYN too
+        assert_eq!(described(&dir, "pie", &elf(2, true, 0, 3, 0x3E)), "ELF 64-bit x86_64 program for Linux");
+        assert_eq!(described(&dir, "old", &elf(1, false, 9, 2, 0x14)), "ELF 32-bit powerpc program for FreeBSD");
+        assert_eq!(described(&dir, "rv", &elf(1, true, 0, 2, 0xF3)), "ELF 32-bit riscv32 program for Linux");
+
+        let mut appimage = elf(2, true, 0, 2, 0x3E);
+        put(&mut appimage, 8, b"AI\x02");
+        assert_eq!(described(&dir, "App.AppImage", &appimage), "AppImage 64-bit x86_64 program for Linux");
+
+        assert!(inspect_bytes(&dir, "bad-class", &elf(7, true, 0, 2, 0x3E)).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn pe_headers_give_machine_dll_flag_and_installers() {
+        let dir = scratch("pe");
+        assert_eq!(described(&dir, "app.exe", &pe(0x8664, 0x0022, 0x20B)), "PE 64-bit x86_64 program for Windows");
+        assert_eq!(described(&dir, "lib.dll", &pe(0x014C, 0x2102, 0x10B)), "PE 32-bit x86 library for Windows");
+        assert_eq!(described(&dir, "arm.exe", &pe(0xAA64, 0x0022, 0x20B)), "PE 64-bit arm64 program for Windows");
+
+        let mut nsis = pe(0x014C, 0x0102, 0x10B);
+        nsis.extend_from_slice(b"....Nullsoft Install System v3.08....");
+        let info = inspect_bytes(&dir, "tool.exe", &nsis).unwrap();
+        assert_eq!(info.kind, ExecKind::Installer);
+        assert_eq!(info.arch, None);
+
+        let plain = pe(0x8664, 0x0022, 0x20B);
+        for name in ["setup.exe", "install.exe", "VSCodeUserSetup-x64-1.90.exe", "app_installer.exe"] {
+            assert_eq!(inspect_bytes(&dir, name, &plain).unwrap().kind, ExecKind::Installer, "{}", name);
+        }
+        for name in ["uninstall.exe", "unins000.exe", "setuptools.exe", "reinstaller.exe"] {
+            assert_eq!(inspect_bytes(&dir, name, &plain).unwrap().kind, ExecKind::Program, "{}", name);
+        }
+
+        // MZ without a PE header is a DOS program we do not describe
+        let mut dos = pe(0x8664, 0, 0x20B);
+        put(&mut dos, 0x80, b"NE\0\0");
+        assert!(inspect_bytes(&dir, "dos.exe", &dos).is_none());
+        let mut far = pe(0x8664, 0, 0x20B);
+        put(&mut far, 0x3C, &u32::MAX.to_le_bytes());
+        assert!(inspect_bytes(&dir, "far.exe", &far).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn macho_and_universal_binaries() {
+        let dir = scratch("macho");
+        assert_eq!(described(&dir, "tool", &macho(0x0100_000C, 2)), "Mach-O 64-bit arm64 program for macOS");
+        assert_eq!(described(&dir, "lib.dylib", &macho(0x0100_0007, 6)), "Mach-O 64-bit x86_64 library for macOS");
+        assert_eq!(described(&dir, "uni", &fat(&[0x0100_0007, 0x0100_000C])), "Mach-O x86_64+arm64 program for macOS");
+
+        // A Java class file: same magic, version where the arch count would be
+        let mut class = 0xCAFE_BABEu32.to_be_bytes().to_vec();
+        class.extend([0, 0, 0, 65]);
+        assert!(inspect_bytes(&dir, "Main.class", &class).is_none());
+        assert!(inspect_bytes(&dir, "empty-fat", &fat(&[])).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scripts_need_a_shebang_and_the_exec_bit() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = scratch("script");
+        let path = dir.join("run");
+        fs::write(&path, b"#!/usr/bin/env -S python3 -u\nprint(1)\n").unwrap();
+        assert!(inspect(&path).is_none());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(inspect(&path).unwrap().to_string(), "script (python3) for Unix");
+
+        fs::write(&path, b"#!/bin/sh").unwrap();
+        assert_eq!(inspect(&path).unwrap().interpreter.as_deref(), Some("sh"));
+        fs::write(&path, b"#!").unwrap();
+        assert_eq!(inspect(&path).unwrap().interpreter, None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn truncated_headers_do_not_panic() {
+        let dir = scratch("truncated");
+        let fixtures = [
+            ("elf", elf(2, true, 0, 2, 0x3E)),
+            ("pe.exe", pe(0x8664, 0x0022, 0x20B)),
+            ("macho", macho(0x0100_000C, 2)),
+            ("fat", fat(&[0x0100_0007, 0x0100_000C])),
+        ];
+        for (name, bytes) in &fixtures {
+            for len in 0..bytes.len() {
+                inspect_bytes(&dir, name, &bytes[..len]);
+            }
+        }
+        // Cut inside the header fields, each of these is not recognised
+        assert!(inspect_bytes(&dir, "elf", &fixtures[0].1[..18]).is_none());
+        assert!(inspect_bytes(&dir, "pe.exe", &fixtures[1].1[..0x86]).is_none());
+        assert!(inspect_bytes(&dir, "macho", &fixtures[2].1[..8]).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn name_words_split_at_punctuation_and_case() {
+        assert_eq!(name_words("VSCodeUserSetup-x64"), ["vscode", "user", "setup", "x64"]);
+        assert_eq!(name_words("my_app.install"), ["my", "app", "install"]);
+        assert_eq!(name_words("uninstall"), ["uninstall"]);
+        assert!(name_words("--").is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::metadata::Metadata;
+
+const MAX_SCAN: u64 = 1024 * 1024;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATETIME_ORIGINAL: u16 = 0x9003;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little = match data.get(..4)? {
+            // Olympus ORF and Panasonic RW2 are TIFF with a magic number of their own
+            b"II*\0" | b"IIRO" | b"IIRS" | b"IIU\0" => true,
+            b"MM\0*" | b"MMOR" => false,
+            _ => return None,
+        };
+        Some(Self { data, little })
+    }
+
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b = self.data.get(at..at + 2)?;
+        Some(if self.little { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b = self.data.get(at..at + 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Some(if self.little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    /// Entries of the IFD at `offset` as `(tag, type, count, value_offset_field_position)`.
+    fn entries(&self, offset: usize) -> Vec<(u16, u16, u32, usize)> {
+        let Some(count) = self.u16(offset) else { return Vec::new() };
+        (0..count.min(512) as usize)
+            .filter_map(|i| {
+                let at = offset + 2 + i * 12;
+                Some((self.u16(at)?, self.u16(at + 2)?, self.u32(at + 4)?, at + 8))
+            })
+            .collect()
+    }
+
+    fn ascii(&self, kind: u16, count: u32, field: usize) -> Option<String> {
+        if kind != 2 {
+            return None;
+        }
+        let count = count as usize;
+        let start = if count <= 4 { field } else { self.u32(field)? as usize };
+        let bytes = self.data.get(start..start + count)?;
+        let text: String = bytes
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+fn parse_tiff(data: &[u8], meta: &mut Metadata) {
+    let Some(tiff) = Tiff::new(data) else { return };
+    let Some(ifd0) = tiff.u32(4) else { return };
+
+    let mut exif_ifd = None;
+    let mut gps_ifd = None;
+
+    for (tag, kind, count, field) in tiff.entries(ifd0 as usize) {
+        match tag {
+            TAG_MAKE => put(meta, "camera_make", tiff.ascii(kind, count, field)),
+            TAG_MODEL => put(meta, "camera_model", tiff.ascii(kind, count, field)),
+            TAG_SOFTWARE => put(meta, "software", tiff.ascii(kind, count, field)),
+            TAG_DATETIME => put(meta, "modified_at", tiff.ascii(kind, count, field)),
+            TAG_EXIF_IFD => exif_ifd = tiff.u32(field),
+            TAG_GPS_IFD => gps_ifd = tiff.u32(field),
+            _ => {}
+        }
+    }
+
+    if let Some(offset) = exif_ifd {
+        for (tag, kind, count, field) in tiff.entries(offset as usize) {
+            match tag {
+                TAG_DATETIME_ORIGINAL => put(meta, "taken_at", tiff.ascii(kind, count, field)),
+                TAG_LENS_MODEL => put(meta, "lens_model", tiff.ascii(kind, count, field)),
+                _ => {}
+            }
+        }
+    }
+
+    let has_gps = gps_ifd.is_some_and(|offset| {
+        tiff.entries(offset as usize)
+            .iter()
+            .any(|(tag, _, _, _)| *tag == TAG_GPS_LATITUDE)
+    });
+    meta.insert("has_gps".to_string(), if has_gps { "yes" } else { "no" }.to_string());
+}
+
+fn put(meta: &mut Metadata, key: &str, value: Option<String>) {
+    if let Some(v) = value {
+        meta.entry(key.to_string()).or_insert(v);
+    }
+}
+
+/// Walks JPEG segments up to the first APP1 `Exif` block.
+fn jpeg_exif(buf: &[u8]) -> Option<&[u8]> {
+    let mut pos = 2;
+    while pos + 4 <= buf.len() && buf[pos] == 0xFF {
+        let marker = buf[pos + 1];
+        let len = u16::from_be_bytes([buf[pos + 2], buf[pos + 3]]) as usize;
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let body = buf.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE1 && body.starts_with(b"Exif\0\0") {
+            return Some(&body[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// HEIC/AVIF keep the Exif item in `mdat`; its payload still starts with `Exif\0\0` + TIFF header.
+fn scan_exif(buf: &[u8]) -> Option<&[u8]> {
+    buf.windows(10)
+        .position(|w| w.starts_with(b"Exif\0\0") && (&w[6..] == b"II*\0" || &w[6..] == b"MM\0*"))
+        .map(|i| &buf[i + 6..])
+}
+
+/// Fujifilm RAF: the big-endian header field at byte 84 points to an embedded JPEG carrying the EXIF.
+fn raf_exif(buf: &[u8]) -> Option<&[u8]> {
+    let at = u32::from_be_bytes(buf.get(84..88)?.try_into().ok()?) as usize;
+    buf.get(at..).filter(|jpeg| jpeg.starts_with(b"\xFF\xD8")).and_then(jpeg_exif)
+}
+
+/// The TIFF structure holding the EXIF of a JPEG, TIFF-based RAW, RAF or HEIC file.
+fn find_tiff(buf: &[u8]) -> Option<&[u8]> {
+    if buf.starts_with(b"\xFF\xD8") {
+        jpeg_exif(buf)
+    } else if Tiff::new(buf).is_some() {
+        Some(buf)
+    } else if buf.starts_with(b"FUJIFILM") {
+        raf_exif(buf).or_else(|| scan_exif(buf))
+    } else if buf.len() >= 12 && &buf[4..8] == b"ftyp" {
+        scan_exif(buf)
+    } else {
+        None
+    }
+}
+
+/// Reads camera, date and GPS presence from JPEG, TIFF-based RAW, RAF and HEIC files.
+pub fn read_exif(path: &Path) -> Result<Metadata> {
+    let mut buf = Vec::new();
+    File::open(path)?.take(MAX_SCAN).read_to_end(&mut buf)?;
+
+    let mut meta = Metadata::new();
+    if let Some(tiff) = find_tiff(&buf) {
+        parse_tiff(tiff, &mut meta);
+    }
+    Ok(meta)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A TIFF block with make, model and an EXIF IFD holding the capture date.
+    pub fn tiff(magic: &[u8; 4], make: &str, model: &str, taken: &str) -> Vec<u8> {
+        let little = magic.starts_with(b"II");
+        let u16b = |v: u16| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32b = |v: u32| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+        let strings = [format!("{}\0", make), format!("{}\0", model), format!("{}\0", taken)];
+        let data_at = 8 + 42 + 18;
+        let at = |i: usize| data_at + strings[..i].iter().map(String::len).sum::<usize>();
+
+        let mut out = magic.to_vec();
+        out.extend(u32b(8));
+        let entry = |out: &mut Vec<u8>, tag: u16, kind: u16, count: usize, value: usize| {
+            out.extend(u16b(tag));
+            out.extend(u16b(kind));
+            out.extend(u32b(count as u32));
+            out.extend(u32b(value as u32));
+        };
+        out.extend(u16b(3));
+        entry(&mut out, TAG_MAKE, 2, strings[0].len(), at(0));
+        entry(&mut out, TAG_MODEL, 2, strings[1].len(), at(1));
+        entry(&mut out, TAG_EXIF_IFD, 4, 1, 8 + 42);
+        out.extend(u32b(0));
+        out.extend(u16b(1));
+        entry(&mut out, TAG_DATETIME_ORIGINAL, 2, strings[2].len(), at(2));
+        out.extend(u32b(0));
+        for s in &strings {
+            out.extend(s.as_bytes());
+        }
+        out
+    }
+
+    /// A JPEG whose APP1 segment carries `tiff`.
+    pub fn jpeg(tiff: &[u8]) -> Vec<u8> {
+        let mut out = b"\xFF\xD8\xFF\xE1".to_vec();
+        out.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        out.extend(b"Exif\0\0");
+        out.extend(tiff);
+        out.extend(b"\xFF\xDA\0\x02\xFF\xD9");
+        out
+    }
+
+    fn read(buf: &[u8]) -> Metadata {
+        let mut meta = Metadata::new();
+        if let Some(tiff) = find_tiff(buf) {
+            parse_tiff(tiff, &mut meta);
+        }
+        meta
+    }
+
+    #[test]
+    fn tiff_byte_orders_and_raw_variants() {
+        for magic in [b"II*\0", b"MM\0*", b"IIRO", b"MMOR", b"IIU\0"] {
+            let meta = read(&tiff(magic, "OLYMPUS", "E-M1", "2024:05:01 10:00:00"));
+            assert_eq!(meta.get("camera_make").map(String::as_str), Some("OLYMPUS"), "{:?}", magic);
+            assert_eq!(meta.get("camera_model").map(String::as_str), Some("E-M1"));
+            assert_eq!(meta.get("taken_at").map(String::as_str), Some("2024:05:01 10:00:00"));
+            assert_eq!(meta.get("has_gps").map(String::as_str), Some("no"));
+        }
+        assert!(read(b"IIXX\x08\0\0\0").is_empty());
+    }
+
+    #[test]
+    fn exif_is_found_in_jpeg_raf_and_heic() {
+        let block = tiff(b"MM\0*", "FUJIFILM", "X-T5", "2023:01:02 03:04:05");
+        let jpeg = jpeg(&block);
+        assert_eq!(read(&jpeg).get("camera_model").map(String::as_str), Some("X-T5"));
+
+        let mut raf = b"FUJIFILMCCD-RAW 0201FF393701X-T5".to_vec();
+        raf.resize(84, 0);
+        raf.extend(100u32.to_be_bytes());
+        raf.extend((jpeg.len() as u32).to_be_bytes());
+        raf.resize(100, 0);
+        raf.extend(&jpeg);
+        assert_eq!(read(&raf).get("camera_model").map(String::as_str), Some("X-T5"));
+
+        // A bad directory offset still finds the EXIF by scanning
+        raf[84..88].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(read(&raf).get("camera_model").map(String::as_str), Some("X-T5"));
+
+        let mut heic = b"\0\0\0\x18ftypheic".to_vec();
+        heic.extend(b"\0\0\0\0Exif\0\0");
+        heic.extend(&block);
+        assert_eq!(read(&heic).get("camera_make").map(String::as_str), Some("FUJIFILM"));
+    }
+
+    #[test]
+    fn truncated_and_hostile_data_is_ignored() {
+        let block = tiff(b"II*\0", "Canon", "EOS R5", "2024:05:01 10:00:00");
+        for len in 0..block.len() {
+            read(&block[..len]);
+        }
+
+        let mut huge = block.clone();
+        huge[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+        read(&huge);
+
+        let mut far = block.clone();
+        far[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(!read(&far).contains_key("camera_make"));
+
+        assert_eq!(jpeg_exif(b"\xFF\xD8\xFF\xE1\0\0"), None);
+        assert_eq!(jpeg_exif(b"\xFF\xD8\xFF\xE1\xFF\xFF"), None);
+    }
+}
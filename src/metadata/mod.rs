@@ -0,0 +1,162 @@
+pub mod audio;
+pub mod bmff;
+pub mod exec;
+pub mod exif;
+pub mod video;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::classify::Category;
+use crate::date::{civil_from_days, civil_from_time};
+
+/// Flat key/value view of everything known about a file, used to fill layout templates.
+pub type Metadata = BTreeMap<String, String>;
+
+pub fn base(path: &Path, ext: &str, category: &Category) -> Metadata {
+    let mut meta = Metadata::new();
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let stem = path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let real_ext = path.extension().map(|e| e.to_string_lossy().into_owned());
+
+    meta.insert("name".to_string(), name);
+    meta.insert("stem".to_string(), stem);
+    meta.insert("ext".to_string(), real_ext.unwrap_or_else(|| ext.to_string()));
+    meta.insert("category".to_string(), category.dir_name().to_string());
+    meta
+}
+
+/// Tags plus the fallbacks audio layouts rely on.
+pub fn audio(path: &Path, mut meta: Metadata) -> Metadata {
+    if let Ok(tags) = audio::read_tags(path) {
+        meta.extend(tags);
+    }
+
+    let artist = meta
+        .get("albumartist")
+        .or_else(|| meta.get("artist"))
+        .cloned()
+        .unwrap_or_else(|| "Unknown Artist".to_string());
+    meta.entry("album_artist".to_string()).or_insert(artist);
+    meta.entry("artist".to_string()).or_insert_with(|| "Unknown Artist".to_string());
+    meta.entry("album".to_string()).or_insert_with(|| "Unknown Album".to_string());
+    let stem = meta.get("stem").cloned().unwrap_or_default();
+    meta.entry("title".to_string()).or_insert(stem);
+    meta
+}
+
+/// Executable format, platform and architecture; `platform` is `Unknown` for unrecognised files.
+pub fn exec(path: &Path, mut meta: Metadata) -> Metadata {
+    match exec::inspect(path) {
+        Some(info) => exec::describe(&info, &mut meta),
+        None => {
+            meta.insert("platform".to_string(), "Unknown".to_string());
+        }
+    }
+    meta
+}
+
+fn insert_date(meta: &mut Metadata, y: i64, m: i64, d: i64) {
+    meta.insert("year".to_string(), format!("{:04}", y));
+    meta.insert("month".to_string(), format!("{:02}", m));
+    meta.insert("day".to_string(), format!("{:02}", d));
+}
+
+/// Fills `year`/`month`/`day` from `date`, or from the file's mtime when the format has none.
+fn insert_date_or_mtime(meta: &mut Metadata, path: &Path, date: Option<(i64, i64, i64)>, source: &str) {
+    let (date, source) = match date {
+        Some(d) => (Some(d), source),
+        None => (fs::metadata(path).and_then(|m| m.modified()).ok().map(civil_from_time), "mtime"),
+    };
+    if let Some((y, m, d)) = date {
+        insert_date(meta, y, m, d);
+        meta.insert("date_source".to_string(), source.to_string());
+    }
+}
+
+/// EXIF `YYYY:MM:DD HH:MM:SS` -> (year, month, day)
+fn parse_exif_date(s: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = s.get(..10)?.split([':', '-']).map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+    (y > 0 && (1..=12).contains(&m) && (1..=31).contains(&d)).then_some((y, m, d))
+}
+
+const SCREENSHOT_HINTS: &[&str] = &["screenshot", "screen shot", "screen_shot", "снимок экрана", "bildschirmfoto"];
+
+/// EXIF fields plus `camera`, date parts and a `photo_kind` of camera, screenshot or edited.
+pub fn photo(path: &Path, mut meta: Metadata) -> Metadata {
+    if let Ok(exif) = exif::read_exif(path) {
+        meta.extend(exif);
+    }
+
+    let make = meta.get("camera_make").cloned();
+    let model = meta.get("camera_model").cloned();
+    let camera = match (&make, &model) {
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model.clone()),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (Some(one), None) | (None, Some(one)) => Some(one.clone()),
+        (None, None) => None,
+    };
+
+    let name = meta.get("name").map(|n| n.to_lowercase()).unwrap_or_default();
+    let kind = match &camera {
+        Some(_) => "camera",
+        None if SCREENSHOT_HINTS.iter().any(|h| name.contains(h)) => "screenshot",
+        None if meta.get("ext").is_some_and(|e| e.eq_ignore_ascii_case("png")) => "screenshot",
+        None => "edited",
+    };
+    meta.insert("photo_kind".to_string(), kind.to_string());
+    meta.insert("camera".to_string(), camera.unwrap_or_else(|| "Unknown Camera".to_string()));
+    meta.entry("camera_model".to_string()).or_insert_with(|| "Unknown Camera".to_string());
+    meta.entry("has_gps".to_string()).or_insert_with(|| "no".to_string());
+
+    let exif_date = ["taken_at", "modified_at"]
+        .iter()
+        .find_map(|k| meta.get(*k).and_then(|v| parse_exif_date(v)));
+    insert_date_or_mtime(&mut meta, path, exif_date, "exif");
+
+    meta
+}
+
+fn resolution_class(width: u32, height: u32) -> &'static str {
+    match width.min(height) {
+        h if h >= 4320 => "8K",
+        h if h >= 2160 => "4K",
+        h if h >= 1440 => "1440p",
+        h if h >= 1080 => "1080p",
+        h if h >= 720 => "720p",
+        _ => "SD",
+    }
+}
+
+/// Duration, resolution and recording date from the container, plus `resolution_class`
+/// (SD..8K) and `length_class` (`Clips` when shorter than `clip_max`, otherwise `Full`).
+pub fn video(path: &Path, mut meta: Metadata, clip_max: Duration) -> Metadata {
+    let info = video::probe(path).unwrap_or_default();
+
+    let length_class = match info.duration_secs {
+        Some(secs) => {
+            meta.insert("duration_secs".to_string(), format!("{:.0}", secs));
+            if secs < clip_max.as_secs_f64() { "Clips" } else { "Full" }
+        }
+        None => "Unknown",
+    };
+    meta.insert("length_class".to_string(), length_class.to_string());
+
+    let class = match (info.width, info.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => {
+            meta.insert("width".to_string(), w.to_string());
+            meta.insert("height".to_string(), h.to_string());
+            meta.insert("resolution".to_string(), format!("{}x{}", w, h));
+            resolution_class(w, h)
+        }
+        _ => "Unknown",
+    };
+    meta.insert("resolution_class".to_string(), class.to_string());
+
+    let created = info.created.map(|secs| civil_from_days(secs.div_euclid(86400)));
+    insert_date_or_mtime(&mut meta, path, created, "container");
+    meta
+}
@@ -0,0 +1,270 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::metadata::bmff::{be, children, find_box};
+
+const MAX_EBML_SCAN: u64 = 4 * 1024 * 1024;
+/// Seconds between 1904-01-01 (QuickTime epoch) and 1970-01-01.
+const MAC_EPOCH_OFFSET: u64 = 2_082_844_800;
+/// Seconds between 1970-01-01 and 2001-01-01 (Matroska DateUTC epoch).
+const MKV_EPOCH_OFFSET: i64 = 978_307_200;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VideoInfo {
+    pub duration_secs: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Unix seconds
+    pub created: Option<i64>,
+}
+
+pub fn probe(path: &Path) -> Result<VideoInfo> {
+    let mut f = File::open(path)?;
+    let mut magic = [0u8; 8];
+    let n = f.read(&mut magic)?;
+
+    if n >= 8 && &magic[4..8] == b"ftyp" {
+        probe_bmff(&mut f)
+    } else if n >= 4 && magic[..4] == [0x1A, 0x45, 0xDF, 0xA3] {
+        probe_matroska(&mut f)
+    } else {
+        Ok(VideoInfo::default())
+    }
+}
+
+fn read_range(f: &mut File, start: u64, end: u64, cap: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    f.seek(SeekFrom::Start(start))?;
+    f.take(end.saturating_sub(start).min(cap)).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn probe_bmff(f: &mut File) -> Result<VideoInfo> {
+    let mut info = VideoInfo::default();
+    let len = f.metadata()?.len();
+    let Some((moov_start, moov_end)) = find_box(f, 0, len, &[b"moov"])? else {
+        return Ok(info);
+    };
+
+    for (kind, start, end) in children(f, moov_start, moov_end)? {
+        match &kind {
+            b"mvhd" => {
+                let b = read_range(f, start, end, 128)?;
+                let (created, timescale, duration) = match b.first() {
+                    Some(1) if b.len() >= 32 => (be(&b[4..12]), be(&b[20..24]), be(&b[24..32])),
+                    Some(_) if b.len() >= 20 => (be(&b[4..8]), be(&b[12..16]), be(&b[16..20])),
+                    _ => continue,
+                };
+                if timescale > 0 {
+                    info.duration_secs = Some(duration as f64 / timescale as f64);
+                }
+                if created > MAC_EPOCH_OFFSET {
+                    info.created = Some((created - MAC_EPOCH_OFFSET) as i64);
+                }
+            }
+            b"trak" => {
+                let Some((tkhd_start, tkhd_end)) = find_box(f, start, end, &[b"tkhd"])? else {
+                    continue;
+                };
+                let b = read_range(f, tkhd_start, tkhd_end, 128)?;
+                // width/height are the last two 16.16 fixed-point fields
+                let dims_at = if b.first() == Some(&1) { 88 } else { 76 };
+                if b.len() < dims_at + 8 {
+                    continue;
+                }
+                let w = (be(&b[dims_at..dims_at + 4]) >> 16) as u32;
+                let h = (be(&b[dims_at + 4..dims_at + 8]) >> 16) as u32;
+                if w > 0 && h > 0 && w >= info.width.unwrap_or(0) {
+                    info.width = Some(w);
+                    info.height = Some(h);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+/// EBML variable-length integer: `(value, length)`; `mask_marker` strips the length bits (sizes).
+fn vint(buf: &[u8], mask_marker: bool) -> Option<(u64, usize)> {
+    let first = *buf.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 || buf.len() < len {
+        return None;
+    }
+    let mut value = if mask_marker { (first as u64) & (0xFF >> len) } else { first as u64 };
+    for &b in &buf[1..len] {
+        value = (value << 8) | b as u64;
+    }
+    Some((value, len))
+}
+
+/// Elements directly inside `buf` as `(id, data)`; an unknown size runs to the end of `buf`.
+fn ebml_elements(buf: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let Some((id, id_len)) = vint(&buf[pos..], false) else { break };
+        let Some((size, size_len)) = vint(&buf[pos + id_len..], true) else { break };
+        let data_start = pos + id_len + size_len;
+        let unknown = size == (1u64 << (7 * size_len)) - 1;
+        let data_end = if unknown { buf.len() } else { (data_start as u64 + size).min(buf.len() as u64) as usize };
+        out.push((id, &buf[data_start.min(buf.len())..data_end]));
+        pos = data_end;
+    }
+    out
+}
+
+fn ebml_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+const ID_SEGMENT: u64 = 0x18538067;
+const ID_INFO: u64 = 0x1549A966;
+const ID_TIMECODE_SCALE: u64 = 0x2AD7B1;
+const ID_DURATION: u64 = 0x4489;
+const ID_DATE_UTC: u64 = 0x4461;
+const ID_TRACKS: u64 = 0x1654AE6B;
+const ID_TRACK_ENTRY: u64 = 0xAE;
+const ID_VIDEO: u64 = 0xE0;
+const ID_PIXEL_WIDTH: u64 = 0xB0;
+const ID_PIXEL_HEIGHT: u64 = 0xBA;
+
+fn probe_matroska(f: &mut File) -> Result<VideoInfo> {
+    let mut info = VideoInfo::default();
+    let buf = read_range(f, 0, u64::MAX, MAX_EBML_SCAN)?;
+
+    let Some((_, segment)) = ebml_elements(&buf).into_iter().find(|(id, _)| *id == ID_SEGMENT) else {
+        return Ok(info);
+    };
+
+    for (id, data) in ebml_elements(segment) {
+        match id {
+            ID_INFO => {
+                let mut scale = 1_000_000f64;
+                let mut duration = None;
+                for (id, data) in ebml_elements(data) {
+                    match id {
+                        ID_TIMECODE_SCALE => scale = be(data) as f64,
+                        ID_DURATION => duration = ebml_float(data),
+                        ID_DATE_UTC if data.len() == 8 => {
+                            let ns = be(data) as i64;
+                            info.created = Some(MKV_EPOCH_OFFSET + ns / 1_000_000_000);
+                        }
+                        _ => {}
+                    }
+                }
+                info.duration_secs = duration.map(|d| d * scale / 1e9);
+            }
+            ID_TRACKS => {
+                for (_, entry) in ebml_elements(data).into_iter().filter(|(id, _)| *id == ID_TRACK_ENTRY) {
+                    for (_, video) in ebml_elements(entry).into_iter().filter(|(id, _)| *id == ID_VIDEO) {
+                        for (id, data) in ebml_elements(video) {
+                            match id {
+                                ID_PIXEL_WIDTH => info.width = Some(be(data) as u32),
+                                ID_PIXEL_HEIGHT => info.height = Some(be(data) as u32),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        if info.duration_secs.is_some() && info.width.is_some() {
+            break;
+        }
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn element(id: u64, data: &[u8]) -> Vec<u8> {
+        let mut out: Vec<u8> = id.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+        match data.len() {
+            n if n < 0x7F => out.push(0x80 | n as u8),
+            n => {
+                out.push(0x01);
+                out.extend(&(n as u64).to_be_bytes()[1..]);
+            }
+        }
+        out.extend(data);
+        out
+    }
+
+    fn matroska(segment_size_unknown: bool) -> Vec<u8> {
+        let info = [
+            element(ID_TIMECODE_SCALE, &[0x0F, 0x42, 0x40]),
+            element(ID_DURATION, &90_500f32.to_be_bytes()),
+            element(ID_DATE_UTC, &(86_400_000_000_000i64).to_be_bytes()),
+        ]
+        .concat();
+        let video = [element(ID_PIXEL_WIDTH, &[0x07, 0x80]), element(ID_PIXEL_HEIGHT, &[0x04, 0x38])].concat();
+        let tracks = element(ID_TRACKS, &element(ID_TRACK_ENTRY, &element(ID_VIDEO, &video)));
+        let body = [element(ID_INFO, &info), tracks].concat();
+
+        let mut file = element(0x1A45DFA3, &element(0x4282, b"matroska"));
+        if segment_size_unknown {
+            file.extend([0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+            file.extend(body);
+        } else {
+            file.extend(element(ID_SEGMENT, &body));
+        }
+        file
+    }
+
+    fn probe_bytes(name: &str, bytes: &[u8]) -> VideoInfo {
+        let path = std::env::temp_dir().join(format!("sortify-video-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        let info = probe(&path).unwrap_or_default();
+        fs::remove_file(&path).ok();
+        info
+    }
+
+    #[test]
+    fn vints_strip_their_length_marker() {
+        assert_eq!(vint(&[0x81], true), Some((1, 1)));
+        assert_eq!(vint(&[0x40, 0x02], true), Some((2, 2)));
+        assert_eq!(vint(&[0x1A, 0x45, 0xDF, 0xA3], false), Some((0x1A45DFA3, 4)));
+        assert_eq!(vint(&[0x00, 1, 2, 3, 4, 5, 6, 7, 8], true), None);
+        assert_eq!(vint(&[0x20, 0x01], true), None);
+        assert_eq!(vint(&[], true), None);
+    }
+
+    #[test]
+    fn matroska_duration_size_and_date() {
+        for unknown in [false, true] {
+            let info = probe_bytes(&format!("mkv-{}", unknown), &matroska(unknown));
+            assert_eq!(info.duration_secs, Some(90.5));
+            assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+            assert_eq!(info.created, Some(MKV_EPOCH_OFFSET + 86_400));
+        }
+    }
+
+    #[test]
+    fn truncated_and_oversized_elements_are_cut_short() {
+        let file = matroska(false);
+        for len in 0..file.len() {
+            probe_bytes("cut", &file[..len]);
+        }
+
+        let mut huge = element(ID_INFO, &[]);
+        huge.truncate(4);
+        huge.extend([0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]);
+        let elements = ebml_elements(&huge);
+        assert_eq!(elements.len(), 1);
+        assert!(elements[0].1.is_empty());
+    }
+}
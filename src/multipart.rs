@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::classify::Category;
+
+const RAR5_SIG: &[u8] = b"Rar!\x1A\x07\x01\x00";
+const RAR4_SIG: &[u8] = b"Rar!\x1A\x07\x00";
+const SEVEN_ZIP_SIG: &[u8] = b"7z\xBC\xAF\x27\x1C";
+const PROBE_LEN: usize = 64;
+/// Index given to the trailing `.zip` of a `.z01`/`.z02`/`.zip` split archive.
+const ZIP_FINAL: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SetKind {
+    /// `name.part1.rar`, `name.part2.rar`, ...
+    RarParts,
+    /// `name.rar`, `name.r00`, `name.r01`, ...
+    RarOld,
+    /// `name.zip`, `name.z01`, `name.z02`, ...
+    ZipSplit,
+    /// `name.7z.001`, `name.mkv.002`, ... (7-Zip and `split` output)
+    Numbered,
+}
+
+impl SetKind {
+    fn first_index(self) -> u32 {
+        match self {
+            SetKind::RarOld => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub index: u32,
+    pub path: PathBuf,
+    /// Length of the shared base name at the start of the file name
+    base_len: usize,
+}
+
+/// Volumes of one multi-part archive or split file, sorted by index.
+#[derive(Debug, Clone)]
+pub struct VolumeSet {
+    pub kind: SetKind,
+    pub volumes: Vec<Volume>,
+}
+
+/// Recognises a volume by name: `(kind, base name, index)`.
+fn parse_name(name: &str) -> Option<(SetKind, &str, u32)> {
+    let lower = name.to_ascii_lowercase();
+    let (stem, ext) = lower.rsplit_once('.')?;
+    let base = &name[..stem.len()];
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    match ext {
+        "rar" => match stem.rsplit_once(".part") {
+            Some((prefix, n)) if digits(n) => Some((SetKind::RarParts, &name[..prefix.len()], n.parse().ok()?)),
+            _ => Some((SetKind::RarOld, base, 0)),
+        },
+        "zip" => Some((SetKind::ZipSplit, base, ZIP_FINAL)),
+        _ if ext.len() >= 3 && ext.starts_with('r') && digits(&ext[1..]) => {
+            Some((SetKind::RarOld, base, ext[1..].parse::<u32>().ok()?.checked_add(1)?))
+        }
+        _ if ext.len() >= 3 && ext.starts_with('z') && digits(&ext[1..]) => {
+            Some((SetKind::ZipSplit, base, ext[1..].parse().ok()?))
+        }
+        _ if ext.len() >= 3 && digits(ext) => Some((SetKind::Numbered, base, ext.parse().ok()?)),
+        _ => None,
+    }
+}
+
+fn read_head_tail(path: &Path) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut f = File::open(path).ok()?;
+    let len = f.metadata().ok()?.len();
+    let mut head = Vec::new();
+    (&mut f).take(PROBE_LEN as u64).read_to_end(&mut head).ok()?;
+    let mut tail = Vec::new();
+    f.seek(SeekFrom::Start(len.saturating_sub(PROBE_LEN as u64))).ok()?;
+    f.read_to_end(&mut tail).ok()?;
+    Some((head, tail))
+}
+
+fn vint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((b & 0x7F) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// What a RAR volume says about itself in its main and end-of-archive headers.
+#[derive(Debug, Default, Clone, Copy)]
+struct RarVolume {
+    is_volume: bool,
+    is_first: bool,
+    /// `Some(true)` when the end header says another volume follows
+    more_follow: Option<bool>,
+}
+
+fn rar_volume(head: &[u8], tail: &[u8]) -> Option<RarVolume> {
+    if head.starts_with(RAR5_SIG) {
+        // CRC32, header size, type (1 = main), header flags, [extra size], [data size], archive flags
+        let mut pos = RAR5_SIG.len() + 4;
+        vint(head, &mut pos)?;
+        if vint(head, &mut pos)? != 1 {
+            return None;
+        }
+        let flags = vint(head, &mut pos)?;
+        if flags & 0x1 != 0 {
+            vint(head, &mut pos)?;
+        }
+        if flags & 0x2 != 0 {
+            vint(head, &mut pos)?;
+        }
+        let archive_flags = vint(head, &mut pos)?;
+        let volume_number = match archive_flags & 0x2 {
+            0 => 0,
+            _ => vint(head, &mut pos)?,
+        };
+        // End of archive: CRC32, size 3, type 5, flags 0, end flags (bit 0 = not last volume)
+        let more_follow = match tail.get(tail.len().saturating_sub(4)..) {
+            Some([3, 5, 0, end_flags]) => Some(end_flags & 0x1 != 0),
+            _ => None,
+        };
+        Some(RarVolume {
+            is_volume: archive_flags & 0x1 != 0,
+            is_first: volume_number == 0,
+            more_follow,
+        })
+    } else if head.starts_with(RAR4_SIG) {
+        // Main header: CRC16, type 0x73, flags (0x0001 volume, 0x0100 first volume)
+        let at = RAR4_SIG.len();
+        if head.get(at + 2) != Some(&0x73) {
+            return None;
+        }
+        let flags = u16::from_le_bytes([*head.get(at + 3)?, *head.get(at + 4)?]);
+        let is_volume = flags & 0x0001 != 0;
+        // End of archive: CRC16, type 0x7B, flags (0x0001 = continued in next volume), size
+        let more_follow = (0..tail.len().saturating_sub(6)).rev().find_map(|p| {
+            let size = u16::from_le_bytes([tail[p + 5], tail[p + 6]]) as usize;
+            (tail[p + 2] == 0x7B && size == tail.len() - p)
+                .then(|| u16::from_le_bytes([tail[p + 3], tail[p + 4]]) & 0x0001 != 0)
+        });
+        Some(RarVolume {
+            is_volume,
+            is_first: !is_volume || flags & 0x0100 != 0,
+            more_follow,
+        })
+    } else {
+        None
+    }
+}
+
+/// Total size a 7z archive needs, from the start header's next-header offset and size.
+fn seven_zip_size(head: &[u8]) -> Option<u64> {
+    if !head.starts_with(SEVEN_ZIP_SIG) || head.len() < 28 {
+        return None;
+    }
+    let offset = u64::from_le_bytes(head[12..20].try_into().ok()?);
+    let size = u64::from_le_bytes(head[20..28].try_into().ok()?);
+    32u64.checked_add(offset)?.checked_add(size)
+}
+
+impl VolumeSet {
+    pub fn first(&self) -> &Path {
+        &self.volumes[0].path
+    }
+
+    /// Display name of the set, e.g. `backup.part1.rar`.
+    pub fn name(&self) -> String {
+        self.first()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.volumes.iter().map(|v| v.path.as_path())
+    }
+
+    /// `None` when every volume is present, otherwise why the set is incomplete.
+    pub fn missing(&self) -> Option<String> {
+        let expected = self.kind.first_index();
+        let first = self.volumes.first()?;
+        if first.index != expected {
+            return Some("first volume missing".to_string());
+        }
+
+        let numbered: Vec<u32> = self
+            .volumes
+            .iter()
+            .map(|v| v.index)
+            .filter(|&i| i != ZIP_FINAL)
+            .collect();
+        if let Some(gap) = numbered.windows(2).find(|w| w[1] != w[0] + 1) {
+            let missing: Vec<String> = (gap[0] + 1..gap[1]).map(|i| self.volume_label(i)).collect();
+            return Some(format!("missing {}", missing.join(", ")));
+        }
+        if self.kind == SetKind::ZipSplit && self.volumes.last()?.index != ZIP_FINAL {
+            return Some("final .zip volume missing".to_string());
+        }
+
+        let (head, _) = read_head_tail(&first.path)?;
+        let last = self.volumes.last()?;
+
+        if let Some(first_rar) = rar_volume(&head, &[]) {
+            if !first_rar.is_first {
+                return Some("first volume missing".to_string());
+            }
+            let last_rar = read_head_tail(&last.path).and_then(|(head, tail)| rar_volume(&head, &tail));
+            if last_rar.and_then(|r| r.more_follow) == Some(true) {
+                return Some(format!("volumes after {} missing", self.volume_label(last.index)));
+            }
+        } else if let Some(needed) = seven_zip_size(&head) {
+            let total: u64 = self
+                .volumes
+                .iter()
+                .filter_map(|v| v.path.metadata().ok())
+                .map(|m| m.len())
+                .sum();
+            if total < needed {
+                return Some(format!("volumes after {} missing", self.volume_label(last.index)));
+            }
+        }
+        None
+    }
+
+    fn volume_label(&self, index: u32) -> String {
+        match self.kind {
+            SetKind::RarOld if index == 0 => ".rar".to_string(),
+            SetKind::RarOld => format!(".r{:02}", index - 1),
+            SetKind::ZipSplit => format!(".z{:02}", index),
+            SetKind::RarParts => format!("part {}", index),
+            SetKind::Numbered => format!(".{:03}", index),
+        }
+    }
+
+    /// Folder the whole set goes to: archives by default, the inner type for split media files.
+    pub fn category(&self) -> Category {
+        if self.kind != SetKind::Numbered {
+            return Category::Archives;
+        }
+        let base = &self.name()[..self.volumes[0].base_len];
+        let inner = Path::new(base)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match Category::from_ext(&inner) {
+            Category::Uncategorized => {
+                let head = read_head_tail(self.first()).map(|(h, _)| h).unwrap_or_default();
+                if head.starts_with(SEVEN_ZIP_SIG) || head.starts_with(b"Rar!") || head.starts_with(b"PK") {
+                    Category::Archives
+                } else {
+                    Category::Uncategorized
+                }
+            }
+            category => category,
+        }
+    }
+
+    /// File names of the set with the shared base name replaced, to move around a collision.
+    pub fn renamed(&self, new_base: impl Fn(&str) -> String) -> Vec<String> {
+        self.volumes
+            .iter()
+            .map(|v| {
+                let name = v.path.file_name().unwrap_or_default().to_string_lossy();
+                format!("{}{}", new_base(&name[..v.base_len]), &name[v.base_len..])
+            })
+            .collect()
+    }
+}
+
+/// Splits `entries` into single files and multi-volume sets.
+///
+/// A lone `.rar` or `.zip` only counts as a set when its headers say it is a volume.
+pub fn group(entries: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<VolumeSet>) {
+    let mut groups: BTreeMap<(PathBuf, SetKind, String), Vec<Volume>> = BTreeMap::new();
+    let mut singles = Vec::new();
+
+    for path in entries {
+        let parsed = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(parse_name)
+            .map(|(kind, base, index)| (kind, base.to_lowercase(), base.len(), index));
+        match parsed {
+            Some((kind, base, base_len, index)) => {
+                let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                groups.entry((dir, kind, base)).or_default().push(Volume { index, path, base_len });
+            }
+            None => singles.push(path),
+        }
+    }
+
+    let mut sets = Vec::new();
+    for ((_, kind, _), mut volumes) in groups {
+        volumes.sort_by_key(|v| v.index);
+        if volumes.len() == 1 && !is_lone_volume(kind, &volumes[0]) {
+            singles.push(volumes.remove(0).path);
+            continue;
+        }
+        sets.push(VolumeSet { kind, volumes });
+    }
+    (singles, sets)
+}
+
+/// Whether a set with a single member is really a (possibly incomplete) multi-volume set.
+fn is_lone_volume(kind: SetKind, volume: &Volume) -> bool {
+    match (kind, volume.index) {
+        (SetKind::RarOld, 0) | (SetKind::RarParts, _) => read_head_tail(&volume.path)
+            .and_then(|(head, tail)| rar_volume(&head, &tail))
+            .is_some_and(|r| r.is_volume || volume.index > kind.first_index()),
+        (SetKind::ZipSplit, ZIP_FINAL) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(names: &[&str]) -> (Vec<PathBuf>, Vec<VolumeSet>) {
+        let dir = Path::new("/nonexistent-sortify-test");
+        group(names.iter().map(|n| dir.join(n)).collect())
+    }
+
+    #[test]
+    fn names_map_to_kind_base_and_index() {
+        assert_eq!(parse_name("Backup.part01.rar"), Some((SetKind::RarParts, "Backup", 1)));
+        assert_eq!(parse_name("backup.rar"), Some((SetKind::RarOld, "backup", 0)));
+        assert_eq!(parse_name("backup.r00"), Some((SetKind::RarOld, "backup", 1)));
+        assert_eq!(parse_name("Backup.R05"), Some((SetKind::RarOld, "Backup", 6)));
+        assert_eq!(parse_name("data.zip"), Some((SetKind::ZipSplit, "data", ZIP_FINAL)));
+        assert_eq!(parse_name("data.z01"), Some((SetKind::ZipSplit, "data", 1)));
+        assert_eq!(parse_name("movie.mkv.002"), Some((SetKind::Numbered, "movie.mkv", 2)));
+        assert_eq!(parse_name("photo.jpg"), None);
+        assert_eq!(parse_name("notes.r1"), None);
+        assert_eq!(parse_name("a.r4294967295"), None);
+    }
+
+    #[test]
+    fn lone_zip_and_rar_stay_single() {
+        let (singles, sets) = set(&["data.zip", "backup.rar"]);
+        assert_eq!(singles.len(), 2);
+        assert!(sets.is_empty());
+    }
+
+    #[test]
+    fn complete_sets_have_nothing_missing() {
+        let (_, sets) = set(&["b.r01", "b.rar", "b.r00"]);
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].kind, SetKind::RarOld);
+        assert_eq!(sets[0].name(), "b.rar");
+        assert_eq!(sets[0].missing(), None);
+
+        let (_, sets) = set(&["d.zip", "d.z01", "d.z02"]);
+        assert_eq!(sets[0].missing(), None);
+    }
+
+    #[test]
+    fn gaps_and_missing_ends_are_reported() {
+        let (_, sets) = set(&["b.rar", "b.r00", "b.r02", "b.r03"]);
+        assert_eq!(sets[0].missing().as_deref(), Some("missing .r01"));
+
+        let (_, sets) = set(&["b.r00", "b.r01"]);
+        assert_eq!(sets[0].missing().as_deref(), Some("first volume missing"));
+
+        let (_, sets) = set(&["m.mkv.001", "m.mkv.004"]);
+        assert_eq!(sets[0].missing().as_deref(), Some("missing .002, .003"));
+
+        let (_, sets) = set(&["d.z01", "d.z02"]);
+        assert_eq!(sets[0].missing().as_deref(), Some("final .zip volume missing"));
+    }
+
+    #[test]
+    fn seven_zip_sizes_do_not_overflow() {
+        let mut head = SEVEN_ZIP_SIG.to_vec();
+        head.extend([0; 6]);
+        head.extend(100u64.to_le_bytes());
+        head.extend(20u64.to_le_bytes());
+        assert_eq!(seven_zip_size(&head), Some(152));
+
+        head[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(seven_zip_size(&head), None);
+        assert_eq!(seven_zip_size(b"7z"), None);
+    }
+}
@@ -1,8 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
-use crate::classify::Category;
+use crate::output;
 
 fn get_unique_path(target: &Path) -> PathBuf {
     if !target.exists() {
@@ -40,34 +43,593 @@ fn get_unique_path(target: &Path) -> PathBuf {
     parent.join(fallback_name)
 }
 
+/// How a file gets to its destination (`--mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+    /// Move the file
+    #[default]
+    Move,
+    /// Copy it with its metadata and leave the original in place
+    Copy,
+    /// Add a hard link to it (same filesystem only); both names share the data
+    Hardlink,
+    /// Leave a symbolic link to the original
+    Symlink,
+    /// Clone it copy-on-write (Btrfs, XFS), or copy it where cloning is not supported
+    Reflink,
+}
+
+impl TransferMode {
+    /// "move", "copy", ... as in "Would move:"
+    pub fn verb(self) -> &'static str {
+        match self {
+            TransferMode::Move => "move",
+            TransferMode::Copy => "copy",
+            TransferMode::Hardlink => "hard-link",
+            TransferMode::Symlink => "link",
+            TransferMode::Reflink => "clone",
+        }
+    }
+
+    /// "Moved", "Copied", ... as in "Moved files:"
+    pub fn past(self) -> &'static str {
+        match self {
+            TransferMode::Move => "Moved",
+            TransferMode::Copy => "Copied",
+            TransferMode::Hardlink => "Hard-linked",
+            TransferMode::Symlink => "Linked",
+            TransferMode::Reflink => "Cloned",
+        }
+    }
+}
+
+/// Moves (or copies, links, clones; see `TransferMode`) `src` to `root/dest`, where `dest` is the
+/// relative path chosen by the layout.
+/// Returns the final target path, which differs from `root/dest` after collision renaming.
 pub fn move_to_category(
     src: &Path,
     root: &Path,
-    category: &Category,
+    dest: &Path,
+    mode: TransferMode,
     dry_run: bool,
-) -> Result<()> {
-    let target_dir = root.join(category.dir_name());
-    let file_name = src.file_name().context("file has no name")?;
-    let mut target_path = target_dir.join(file_name);
+) -> Result<PathBuf> {
+    let mut target_path = root.join(dest);
+    let target_dir = target_path
+        .parent()
+        .context("destination has no parent directory")?
+        .to_path_buf();
 
     if dry_run {
-        return Ok(());
+        return Ok(target_path);
     }
 
     fs::create_dir_all(&target_dir)
         .with_context(|| format!("cannot create dir {}", target_dir.display()))?;
 
     if target_path.exists() {
-        eprintln!(
-            "File already exists: {}",
-            target_path.display()
-        );
+        output::verbose(format!("File already exists: {}", target_path.display()));
         target_path = get_unique_path(&target_path);
-        eprintln!("   Renaming to: {}", target_path.file_name().unwrap().to_string_lossy());
+        output::verbose(format!("   Renaming to: {}", target_path.file_name().unwrap().to_string_lossy()));
+    }
+
+    transfer(src, &target_path, mode)?;
+
+    Ok(target_path)
+}
+
+/// Puts `src` at `target` the way `mode` says. Symbolic links are carried over as links
+/// by every mode but `symlink`, which links to them instead.
+pub fn transfer(src: &Path, target: &Path, mode: TransferMode) -> Result<()> {
+    let context = || format!("cannot {} {} to {}", mode.verb(), src.display(), target.display());
+    let is_link = fs::symlink_metadata(src).with_context(context)?.file_type().is_symlink();
+    match mode {
+        TransferMode::Move => move_file(src, target),
+        TransferMode::Copy | TransferMode::Reflink if is_link => {
+            make_symlink(&fs::read_link(src).with_context(context)?, target)
+        }
+        TransferMode::Copy => copy_preserving(src, target),
+        TransferMode::Hardlink => fs::hard_link(src, target).with_context(context),
+        TransferMode::Symlink => make_symlink(&std::path::absolute(src).with_context(context)?, target),
+        TransferMode::Reflink => match reflink(src, target) {
+            Ok(()) => {
+                let meta = fs::metadata(src).with_context(context)?;
+                if let Err(err) = copy_attributes(src, target, &meta) {
+                    output::verbose(format!("{}: metadata not fully preserved: {}", target.display(), err));
+                }
+                Ok(())
+            }
+            Err(err) => {
+                output::verbose(format!("{}: cannot clone ({}); copying instead", src.display(), err));
+                copy_preserving(src, target)
+            }
+        },
+    }
+}
+
+/// Shares `src`'s data with a new file at `target` through the FICLONE ioctl.
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, target: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let from = fs::File::open(src)?;
+    let to = fs::OpenOptions::new().write(true).create_new(true).open(target)?;
+    // SAFETY: both descriptors are open for the duration of the call
+    if unsafe { libc::ioctl(to.as_raw_fd(), libc::FICLONE, from.as_raw_fd()) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    drop(to);
+    let _ = fs::remove_file(target);
+    Err(err)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reflinks are only supported on Linux"))
+}
+
+/// Renames `src` to `target`. Across filesystems the file is copied with its metadata
+/// (see `copy_preserving`) and the original removed once the copy is complete.
+pub fn move_file(src: &Path, target: &Path) -> Result<()> {
+    let context = || format!("cannot move {} to {}", src.display(), target.display());
+    match fs::rename(src, target) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+        other => return other.with_context(context),
+    }
+
+    let is_link = fs::symlink_metadata(src).with_context(context)?.file_type().is_symlink();
+    if is_link {
+        let link = fs::read_link(src).with_context(context)?;
+        make_symlink(&link, target)?;
+    } else {
+        copy_preserving(src, target).with_context(context)?;
+    }
+    fs::remove_file(src).with_context(|| format!("copied {} but cannot remove it", src.display()))
+}
+
+/// Copies `src` to `target` with its access and modification times, permissions, ownership
+/// (where permitted) and extended attributes. Only the contents must make it across;
+/// metadata that cannot be set is reported at `-v`.
+pub fn copy_preserving(src: &Path, target: &Path) -> Result<()> {
+    let meta = fs::metadata(src).with_context(|| format!("cannot read metadata of {}", src.display()))?;
+    if let Err(err) = fs::copy(src, target) {
+        let _ = fs::remove_file(target);
+        return Err(err).with_context(|| format!("cannot copy {} to {}", src.display(), target.display()));
+    }
+
+    if let Err(err) = copy_attributes(src, target, &meta) {
+        output::verbose(format!("{}: metadata not fully preserved: {}", target.display(), err));
     }
+    Ok(())
+}
+
+/// The copy stays writable by its owner until the xattrs and times are on it, since both
+/// need write access (and Windows only sets times through a handle opened for writing). The
+/// mode goes on last, after chown, which clears set-id bits, and also when an earlier step failed.
+fn copy_attributes(src: &Path, target: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    #[cfg(unix)]
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    #[cfg(unix)]
+    fs::set_permissions(target, fs::Permissions::from_mode(0o600))?;
+    #[cfg(not(unix))]
+    if meta.permissions().readonly() {
+        let mut writable = meta.permissions();
+        writable.set_readonly(false);
+        fs::set_permissions(target, writable)?;
+    }
+
+    #[cfg(unix)]
+    let listed = xattr::list_deref(src).map(|names| {
+        for name in names {
+            // security.* and trusted.* often need privileges; copy what is allowed
+            if let Ok(Some(value)) = xattr::get_deref(src, &name) {
+                let _ = xattr::set(target, &name, &value);
+            }
+        }
+    });
+    #[cfg(not(unix))]
+    let listed = Ok(());
+
+    let timed = meta.modified().and_then(|modified| {
+        let mut times = fs::FileTimes::new().set_modified(modified);
+        if let Ok(accessed) = meta.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        fs::OpenOptions::new().write(true).open(target)?.set_times(times)
+    });
 
-    fs::rename(src, &target_path)
-        .with_context(|| format!("cannot move {} to {}", src.display(), target_path.display()))?;
+    #[cfg(unix)]
+    {
+        // Only root may give a file away; keeping the copy's owner is the fallback
+        let _ = std::os::unix::fs::chown(target, Some(meta.uid()), Some(meta.gid()));
+        fs::set_permissions(target, fs::Permissions::from_mode(meta.mode() & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    fs::set_permissions(target, meta.permissions())?;
+    listed.and(timed)
+}
+
+/// Leaves a quarantined file readable by its owner only: no write or execute bits for anyone.
+#[cfg(unix)]
+pub fn strip_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o400))
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub fn strip_permissions(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+/// Permission bits of `path`, so a later undo can put them back.
+#[cfg(unix)]
+pub fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).ok().map(|m| m.permissions().mode() & 0o7777)
+}
 
+#[cfg(not(unix))]
+pub fn file_mode(path: &Path) -> Option<u32> {
+    fs::metadata(path)
+        .ok()
+        .map(|m| if m.permissions().readonly() { 0o444 } else { 0o644 })
+}
+
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+/// Moves (see `TransferMode`) every `(src, target)` pair or none of them: on failure the files
+/// already moved are put back, and copies or links already made are removed. Files the rollback
+/// could not deal with are named in the error.
+pub fn move_all(moves: &[(PathBuf, PathBuf)], mode: TransferMode, dry_run: bool) -> Result<()> {
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut done: Vec<(&Path, &Path)> = Vec::new();
+    for (src, target) in moves {
+        let step = target
+            .parent()
+            .context("destination has no parent directory")
+            .and_then(|dir| {
+                fs::create_dir_all(dir).with_context(|| format!("cannot create dir {}", dir.display()))
+            })
+            .and_then(|_| transfer(src, target, mode));
+
+        if let Err(err) = step {
+            let stuck: Vec<String> = done
+                .into_iter()
+                .rev()
+                .filter_map(|(src, target)| {
+                    let undone = match mode {
+                        TransferMode::Move => move_file(target, src),
+                        _ => fs::remove_file(target).map_err(Into::into),
+                    };
+                    undone.err().map(|undo| format!("{} ({:#})", target.display(), undo))
+                })
+                .collect();
+            if stuck.is_empty() {
+                return Err(err);
+            }
+            return Err(anyhow!("{:#}; rolling back left {} in place", err, stuck.join(", ")));
+        }
+        done.push((src, target));
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// What to do with symbolic links found among the files to sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SymlinkPolicy {
+    /// Leave links where they are
+    #[default]
+    Skip,
+    /// Move the link itself; relative targets are rewritten so the link still resolves
+    MoveLink,
+    /// Sort the file the link points to; a move copies it and then removes the link
+    FollowTarget,
+}
+
+/// Name for a FIFO, socket or device node, which are never sorted.
+#[cfg(unix)]
+pub fn special_kind(kind: &fs::FileType) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+    if kind.is_fifo() {
+        "named pipe (FIFO)"
+    } else if kind.is_socket() {
+        "socket"
+    } else if kind.is_block_device() {
+        "block device"
+    } else if kind.is_char_device() {
+        "character device"
+    } else {
+        "special file"
+    }
+}
+
+#[cfg(not(unix))]
+pub fn special_kind(_kind: &fs::FileType) -> &'static str {
+    "special file"
+}
+
+/// `(device, inode)` and link count of a file with more than one name.
+#[cfg(unix)]
+pub fn hard_link(meta: &fs::Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.nlink() > 1).then(|| ((meta.dev(), meta.ino()), meta.nlink()))
+}
+
+#[cfg(not(unix))]
+pub fn hard_link(_meta: &fs::Metadata) -> Option<((u64, u64), u64)> {
+    None
+}
+
+#[cfg(unix)]
+pub fn make_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("cannot create link {} -> {}", link.display(), target.display()))
+}
+
+#[cfg(windows)]
+pub fn make_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+        .with_context(|| format!("cannot create link {} -> {}", link.display(), target.display()))
+}
+
+/// After a link moved out of `old_dir`, points it at its target by absolute path if it was relative.
+pub fn keep_link_target(link: &Path, old_dir: &Path) -> Result<()> {
+    let target = fs::read_link(link).with_context(|| format!("cannot read link {}", link.display()))?;
+    if target.is_absolute() {
+        return Ok(());
+    }
+    fs::remove_file(link).with_context(|| format!("cannot replace link {}", link.display()))?;
+    make_symlink(&old_dir.join(target), link)
+}
+
+const TAG_CATEGORY: &str = "user.sortify.category";
+const TAG_SOURCE: &str = "user.sortify.source";
+
+/// Where a file came from, as recorded by `--tag`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Provenance {
+    pub category: String,
+    pub source: PathBuf,
+}
+
+/// Records `category` and the original path `source` in `user.sortify.*` xattrs on `path`.
+#[cfg(unix)]
+pub fn tag(path: &Path, category: &str, source: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    xattr::set(path, TAG_CATEGORY, category.as_bytes())?;
+    xattr::set(path, TAG_SOURCE, source.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn tag(_path: &Path, _category: &str, _source: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "extended attributes are not supported here"))
+}
+
+/// Drops the `--tag` attributes, e.g. once a file is back where it came from.
+#[cfg(unix)]
+pub fn untag(path: &Path) {
+    let _ = xattr::remove(path, TAG_CATEGORY);
+    let _ = xattr::remove(path, TAG_SOURCE);
+}
+
+#[cfg(not(unix))]
+pub fn untag(_path: &Path) {}
+
+/// The `--tag` attributes of `path`, if it has them.
+#[cfg(unix)]
+pub fn provenance(path: &Path) -> Option<Provenance> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    let category = xattr::get_deref(path, TAG_CATEGORY).ok()??;
+    let source = xattr::get_deref(path, TAG_SOURCE).ok()??;
+    Some(Provenance {
+        category: String::from_utf8_lossy(&category).into_owned(),
+        source: PathBuf::from(OsStr::from_bytes(&source)),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn provenance(_path: &Path) -> Option<Provenance> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn scratch_in(base: &Path, name: &str) -> PathBuf {
+        let dir = base.join(format!("sortify-ops-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        scratch_in(&std::env::temp_dir(), name)
+    }
+
+    /// A file with an old mtime, `mode` and (where the filesystem takes them) a user xattr;
+    /// returns whether the xattr could be set.
+    fn aged_file(path: &Path, mode: u32) -> bool {
+        fs::write(path, "contents").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+        #[cfg(unix)]
+        let tagged = xattr::set(path, "user.sortify.test", b"kept").is_ok();
+        #[cfg(not(unix))]
+        let tagged = false;
+        set_mode(path, mode).unwrap();
+        tagged
+    }
+
+    fn assert_attributes_kept(path: &Path, mode: u32, tagged: bool) {
+        let meta = fs::metadata(path).unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert_eq!(meta.modified().unwrap(), old, "{}", path.display());
+        assert_eq!(file_mode(path), Some(mode), "{}", path.display());
+        #[cfg(unix)]
+        if tagged {
+            assert_eq!(xattr::get(path, "user.sortify.test").unwrap().as_deref(), Some(&b"kept"[..]));
+        }
+        let _ = tagged;
+        assert_eq!(fs::read_to_string(path).unwrap(), "contents");
+    }
+
+    #[test]
+    fn copies_keep_times_mode_and_xattrs() {
+        let dir = scratch("copy-attrs");
+        for (name, mode) in [("plain", 0o640), ("readonly", 0o444), ("script", 0o750)] {
+            let src = dir.join(name);
+            let tagged = aged_file(&src, mode);
+            let target = dir.join(format!("{}.copy", name));
+            copy_preserving(&src, &target).unwrap();
+            assert_attributes_kept(&target, mode, tagged);
+            assert_attributes_kept(&src, mode, tagged);
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    /// `/dev/shm` is a tmpfs on Linux, and usually not where the temp dir lives.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn moves_across_filesystems_keep_times_mode_and_xattrs() {
+        use std::os::unix::fs::MetadataExt;
+        let shm = Path::new("/dev/shm");
+        let dir = scratch("cross-device");
+        let same_device = fs::metadata(shm).map(|m| m.dev()).ok() == Some(fs::metadata(&dir).unwrap().dev());
+        if !shm.is_dir() || same_device {
+            eprintln!("skipped: no second filesystem at /dev/shm");
+            return;
+        }
+        let other = scratch_in(shm, "cross-device");
+
+        let src = dir.join("report.pdf");
+        let tagged = aged_file(&src, 0o444);
+        let target = other.join("report.pdf");
+        move_file(&src, &target).unwrap();
+        assert!(!src.exists());
+        assert_attributes_kept(&target, 0o444, tagged);
+
+        // And back again, through transfer
+        transfer(&target, &src, TransferMode::Move).unwrap();
+        assert_attributes_kept(&src, 0o444, tagged);
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(&other).ok();
+    }
+
+    #[test]
+    fn each_mode_places_the_file_its_own_way() {
+        let dir = scratch("modes");
+        for mode in [TransferMode::Move, TransferMode::Copy, TransferMode::Hardlink, TransferMode::Symlink, TransferMode::Reflink] {
+            let src = dir.join(format!("{}.txt", mode.verb()));
+            fs::write(&src, mode.verb()).unwrap();
+            let target = move_to_category(&src, &dir, Path::new("Documents/file.txt"), mode, false).unwrap();
+            assert_eq!(fs::read_to_string(&target).unwrap(), mode.verb());
+            assert_eq!(src.exists(), mode != TransferMode::Move, "{:?}", mode);
+
+            let linked = fs::symlink_metadata(&target).unwrap().file_type().is_symlink();
+            assert_eq!(linked, mode == TransferMode::Symlink, "{:?}", mode);
+            if mode == TransferMode::Symlink {
+                assert_eq!(fs::read_link(&target).unwrap(), src);
+            }
+            #[cfg(unix)]
+            if mode != TransferMode::Move {
+                use std::os::unix::fs::MetadataExt;
+                let (a, b) = (fs::metadata(&src).unwrap(), fs::symlink_metadata(&target).unwrap());
+                let shared = a.ino() == b.ino() && a.dev() == b.dev();
+                assert_eq!(shared, mode == TransferMode::Hardlink, "{:?}", mode);
+            }
+            // A clone or copy is independent of the original
+            if matches!(mode, TransferMode::Copy | TransferMode::Reflink) {
+                fs::write(&target, "changed").unwrap();
+                assert_eq!(fs::read_to_string(&src).unwrap(), mode.verb());
+            }
+        }
+        // Later files with the same name get their own
+        assert!(dir.join("Documents/file_4.txt").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn reflinks_fall_back_to_copying() {
+        let dir = scratch("reflink");
+        let src = dir.join("a.bin");
+        let tagged = aged_file(&src, 0o640);
+        let target = dir.join("b.bin");
+        // Supported or not, the result is an independent file with the original's metadata
+        let cloned = reflink(&src, &target);
+        if cloned.is_err() {
+            assert!(!target.exists(), "failed clone left {}", target.display());
+        }
+        fs::remove_file(&target).ok();
+        transfer(&src, &target, TransferMode::Reflink).unwrap();
+        assert_attributes_kept(&target, 0o640, tagged);
+        assert!(src.exists());
+
+        // An existing target is never overwritten
+        assert!(reflink(&src, &target).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "contents");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_of_links_stay_links() {
+        let dir = scratch("copy-link");
+        let link = dir.join("link");
+        make_symlink(Path::new("../elsewhere/file"), &link).unwrap();
+        for mode in [TransferMode::Copy, TransferMode::Reflink] {
+            let target = dir.join(format!("{}.link", mode.verb()));
+            transfer(&link, &target, mode).unwrap();
+            assert_eq!(fs::read_link(&target).unwrap(), Path::new("../elsewhere/file"));
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn move_all_rolls_back_when_a_companion_fails() {
+        let dir = scratch("move-all");
+        let names = ["set.part1.rar", "set.part2.rar", "set.part3.rar"];
+        for mode in [TransferMode::Move, TransferMode::Copy, TransferMode::Hardlink] {
+            for name in &names[..2] {
+                fs::write(dir.join(name), name).unwrap();
+            }
+            // part3 is missing, so the set cannot be completed
+            let moves: Vec<_> = names.iter().map(|n| (dir.join(n), dir.join("Archives").join(n))).collect();
+            let err = move_all(&moves, mode, false).unwrap_err();
+            assert!(format!("{:#}", err).contains("set.part3.rar"), "{:#}", err);
+            assert!(!format!("{:#}", err).contains("in place"), "{:#}", err);
+            for name in &names[..2] {
+                assert_eq!(fs::read_to_string(dir.join(name)).unwrap(), *name, "{:?}", mode);
+                assert!(!dir.join("Archives").join(name).exists(), "{:?}", mode);
+            }
+        }
+
+        // A dry run touches nothing, even for a set that could not be moved
+        let moves = vec![(dir.join("missing"), dir.join("Archives/missing"))];
+        move_all(&moves, TransferMode::Move, true).unwrap();
+        fs::remove_dir_all(&dir).ok();
+    }
+}
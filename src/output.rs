@@ -0,0 +1,64 @@
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+
+/// How much sortify prints. The report goes to stdout; banner, progress and everything
+/// else goes to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// `-q`: no banner, progress or lists; only failures, on stderr
+    Quiet,
+    Normal,
+    /// `-v`: also metadata of moved files and collision renames
+    Verbose,
+    /// `-vv`: also how each file was detected and placed
+    Trace,
+}
+
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+/// Sets the verbosity from `-q`/`-v` and turns colors off when `NO_COLOR` is set.
+pub fn init(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => Verbosity::Quiet,
+        (false, 0) => Verbosity::Normal,
+        (false, 1) => Verbosity::Verbose,
+        (false, _) => Verbosity::Trace,
+    };
+    let _ = VERBOSITY.set(level);
+
+    // https://no-color.org: any non-empty value
+    if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn verbosity() -> Verbosity {
+    VERBOSITY.get().copied().unwrap_or(Verbosity::Normal)
+}
+
+/// Progress bars only make sense when a person is watching the terminal.
+pub fn progress_enabled() -> bool {
+    verbosity() >= Verbosity::Normal && io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Status chatter, shown unless `-q`.
+pub fn info(msg: impl Display) {
+    if verbosity() >= Verbosity::Normal {
+        eprintln!("{}", msg);
+    }
+}
+
+/// Shown with `-v`.
+pub fn verbose(msg: impl Display) {
+    if verbosity() >= Verbosity::Verbose {
+        eprintln!("{}", msg);
+    }
+}
+
+/// Shown with `-vv`.
+pub fn trace(msg: impl Display) {
+    if verbosity() >= Verbosity::Trace {
+        eprintln!("{}", msg);
+    }
+}
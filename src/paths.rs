@@ -0,0 +1,35 @@
+use std::env;
+use std::path::PathBuf;
+
+fn env_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env_dir("HOME").or_else(|| env_dir("USERPROFILE"))
+}
+
+fn base_dir(xdg_var: &str, windows_var: &str, home_fallback: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env_dir(windows_var);
+    }
+    env_dir(xdg_var).or_else(|| home_dir().map(|h| h.join(home_fallback)))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", "APPDATA", ".config").map(|d| d.join("sortify"))
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", "LOCALAPPDATA", ".cache").map(|d| d.join("sortify"))
+}
+
+pub fn state_dir() -> Option<PathBuf> {
+    base_dir("XDG_STATE_HOME", "LOCALAPPDATA", ".local/state").map(|d| d.join("sortify"))
+}
+
+pub fn env_flag(var: &str) -> bool {
+    env::var(var).is_ok_and(|v| !v.is_empty() && v != "0" && !v.eq_ignore_ascii_case("false"))
+}
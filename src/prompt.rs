@@ -22,9 +22,9 @@ pub enum BinaryPolicy {
 }
 
 impl BinaryPolicy {
-    pub fn decide(self, file: &Path) -> Result<(BinaryAction, BinaryPolicy)> {
+    pub fn decide(self, file: &Path, details: Option<&str>) -> Result<(BinaryAction, BinaryPolicy)> {
         match self {
-            BinaryPolicy::AskEvery => ask_binary_policy_once(file),
+            BinaryPolicy::AskEvery => ask_binary_policy_once(file, details),
             BinaryPolicy::SkipAll => {
                 eprintln!(
                     "{} {}",
@@ -38,12 +38,15 @@ impl BinaryPolicy {
     }
 }
 
-pub fn ask_binary_policy_once(file: &Path) -> Result<(BinaryAction, BinaryPolicy)> {
-    println!(
+pub fn ask_binary_policy_once(file: &Path, details: Option<&str>) -> Result<(BinaryAction, BinaryPolicy)> {
+    eprintln!(
         "\n{} {}",
         "Binary file detected:".bright_yellow().bold(),
         file.display()
     );
+    if let Some(details) = details {
+        eprintln!("Type: {}", details.cyan());
+    }
 
     let options = &[
         "Skip this file",
@@ -52,7 +55,7 @@ pub fn ask_binary_policy_once(file: &Path) -> Result<(BinaryAction, BinaryPolicy
         "Always process binary files without asking",
     ];
 
-    let choice = Select::with_theme(&PlainTheme::default())
+    let choice = Select::with_theme(&PlainTheme)
         .with_prompt("Choose an option")
         .items(options)
         .default(0)
@@ -61,19 +64,19 @@ pub fn ask_binary_policy_once(file: &Path) -> Result<(BinaryAction, BinaryPolicy
 
     let result = match choice {
         0 => {
-            println!("{}", "This binary file will be skipped once.".dimmed());
+            eprintln!("{}", "This binary file will be skipped once.".dimmed());
             (BinaryAction::Skip, BinaryPolicy::AskEvery)
         }
         1 => {
-            println!("{}", "All binary files will be skipped automatically.".dimmed());
+            eprintln!("{}", "All binary files will be skipped automatically.".dimmed());
             (BinaryAction::Skip, BinaryPolicy::SkipAll)
         }
         2 => {
-            println!("{}", "This binary file will be processed (will ask next time).".dimmed());
+            eprintln!("{}", "This binary file will be processed (will ask next time).".dimmed());
             (BinaryAction::Process, BinaryPolicy::AskEvery)
         }
         3 => {
-            println!("{}", "All binary files will be processed automatically.".dimmed());
+            eprintln!("{}", "All binary files will be processed automatically.".dimmed());
             (BinaryAction::Process, BinaryPolicy::NeverSkip)
         }
         _ => unreachable!(),
@@ -95,13 +98,13 @@ pub fn ask_conflict_resolution(
     sig_ext: &str,
     real_ext: &str,
 ) -> Result<ConflictResolution> {
-    println!(
+    eprintln!(
         "\n{}",
         "Detected mismatch between extension and file signature:".bright_red().bold()
     );
-    println!("File: {}", file.display());
-    println!("Declared extension: .{}", real_ext.cyan());
-    println!("Detected signature: .{}", sig_ext.cyan());
+    eprintln!("File: {}", file.display());
+    eprintln!("Declared extension: .{}", real_ext.cyan());
+    eprintln!("Detected signature: .{}", sig_ext.cyan());
 
     let options = &[
         "Skip this file",
@@ -110,7 +113,7 @@ pub fn ask_conflict_resolution(
         "Move to manual verification folder",
     ];
 
-    let choice = Select::with_theme(&PlainTheme::default())
+    let choice = Select::with_theme(&PlainTheme)
         .with_prompt("Choose an option")
         .items(options)
         .default(0)
@@ -119,11 +122,11 @@ pub fn ask_conflict_resolution(
 
     let res = match choice {
         0 => {
-            println!("{}", "File skipped.".dimmed());
+            eprintln!("{}", "File skipped.".dimmed());
             ConflictResolution::Skip
         }
         1 => {
-            println!(
+            eprintln!(
                 "{} .{}",
                 "File will be sorted based on signature".green(),
                 sig_ext.bold()
@@ -131,7 +134,7 @@ pub fn ask_conflict_resolution(
             ConflictResolution::BySignature(sig_ext.to_string())
         }
         2 => {
-            println!(
+            eprintln!(
                 "{} .{}",
                 "File will be sorted based on extension".green(),
                 real_ext.bold()
@@ -139,11 +142,55 @@ pub fn ask_conflict_resolution(
             ConflictResolution::ByExtension(real_ext.to_string())
         }
         3 => {
-            println!("{}", "File will be moved to manual verification folder.".dimmed());
+            eprintln!("{}", "File will be moved to manual verification folder.".dimmed());
             ConflictResolution::Mismatched
         }
         _ => unreachable!(),
     };
 
     Ok(res)
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LearnedAction {
+    Accept,
+    AcceptAll,
+    Usual,
+    Skip,
+}
+
+pub fn ask_learned_destination(file: &Path, folder: &Path, confidence: u8, usual: &Path) -> Result<LearnedAction> {
+    eprintln!(
+        "\n{} {}",
+        "Learned destination for".bright_cyan().bold(),
+        file.display()
+    );
+    eprintln!("Proposed: {} {}", folder.display().to_string().cyan(), format!("({}% confident)", confidence).dimmed());
+
+    let options = &[
+        format!("Move to {}", folder.display()),
+        "Accept this and all further proposals".to_string(),
+        format!("Sort as usual ({})", usual.display()),
+        "Skip this file".to_string(),
+    ];
+
+    let choice = Select::with_theme(&PlainTheme)
+        .with_prompt("Choose an option")
+        .items(options)
+        .default(0)
+        .interact()
+        .context("failed to read user input")?;
+
+    Ok(match choice {
+        0 => LearnedAction::Accept,
+        1 => {
+            eprintln!("{}", "Further proposals will be accepted automatically.".dimmed());
+            LearnedAction::AcceptAll
+        }
+        2 => LearnedAction::Usual,
+        3 => {
+            eprintln!("{}", "File skipped.".dimmed());
+            LearnedAction::Skip
+        }
+        _ => unreachable!(),
+    })
+}
@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::archive::list;
+use crate::classify::Category;
+use crate::metadata::exec;
+
+/// OLE2 documents are scanned this far for VBA storage names.
+const MAX_OLE_SCAN: u64 = 8 * 1024 * 1024;
+/// HTML and SVG files are scanned this far for scripts.
+const MAX_MARKUP_SCAN: u64 = 1024 * 1024;
+
+/// Extensions Windows runs (or opens with a script host) on double-click.
+const RUNNABLE_EXTS: &[&str] = &[
+    "exe", "scr", "com", "bat", "cmd", "pif", "vbs", "vbe", "js", "jse", "wsf", "wsh", "msi",
+    "lnk", "ps1", "hta", "jar", "cpl", "reg",
+];
+const OOXML_EXTS: &[&str] = &["docx", "docm", "dotm", "xlsx", "xlsm", "xltm", "xlam", "pptx", "pptm", "potm", "ppam"];
+const OLE_DOC_EXTS: &[&str] = &["doc", "dot", "xls", "xlt", "xla", "ppt", "pot", "pps"];
+const MARKUP_EXTS: &[&str] = &["html", "htm", "xhtml", "svg", "shtml"];
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+
+/// Bidirectional control characters that can make `exe.pdf` display as `fdp.exe`.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Shows bidirectional control characters as `<U+202E>` so a name displays as it really is.
+pub fn escape_bidi(name: &str) -> String {
+    name.chars()
+        .map(|c| match is_bidi_control(c) {
+            true => format!("<U+{:04X}>", c as u32),
+            false => c.to_string(),
+        })
+        .collect()
+}
+
+/// Whether the extension names a file people open to read or view rather than run.
+fn looks_harmless(ext: &str) -> bool {
+    matches!(
+        Category::from_ext(ext),
+        Category::Documents | Category::Pictures | Category::Audio | Category::Video | Category::Archives
+    )
+}
+
+fn read_head(path: &Path, limit: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Ok(f) = File::open(path) {
+        let _ = f.take(limit).read_to_end(&mut buf);
+    }
+    buf
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn check_name(name: &str, reasons: &mut Vec<String>) {
+    if name.chars().any(is_bidi_control) {
+        reasons.push("name contains a right-to-left override character".to_string());
+    }
+
+    let parts: Vec<&str> = name.split('.').map(str::trim).collect();
+    if let [.., inner, outer] = parts.as_slice()
+        && parts.len() > 2
+    {
+        let (inner, outer) = (inner.to_ascii_lowercase(), outer.to_ascii_lowercase());
+        if RUNNABLE_EXTS.contains(&outer.as_str()) && looks_harmless(&inner) {
+            reasons.push(format!("double extension .{}.{}", inner, outer));
+        }
+    }
+}
+
+fn check_content(path: &Path, ext: &str, reasons: &mut Vec<String>) {
+    if looks_harmless(ext)
+        && let Some(info) = exec::inspect(path)
+    {
+        reasons.push(format!("{} disguised as .{}", info, ext));
+    }
+
+    if OOXML_EXTS.contains(&ext)
+        && let Ok(listing) = list::zip(path)
+        && listing.entries.iter().any(|e| e.name.to_ascii_lowercase().ends_with("vbaproject.bin"))
+    {
+        reasons.push("Office document contains VBA macros".to_string());
+    }
+
+    if OLE_DOC_EXTS.contains(&ext) {
+        let buf = read_head(path, MAX_OLE_SCAN);
+        if buf.starts_with(OLE_MAGIC) && contains(&buf, &utf16le("_VBA_PROJECT")) {
+            reasons.push("Office document contains VBA macros".to_string());
+        }
+    }
+
+    if MARKUP_EXTS.contains(&ext) {
+        let text = String::from_utf8_lossy(&read_head(path, MAX_MARKUP_SCAN)).to_ascii_lowercase();
+        let has_handler = ext.starts_with("svg") && [" onload=", " onclick=", " onerror=", " onmouseover="].iter().any(|h| text.contains(h));
+        if text.contains("<script") || text.contains("javascript:") || has_handler {
+            reasons.push(format!("{} file with embedded script", ext.to_ascii_uppercase()));
+        }
+    }
+}
+
+/// Reasons to quarantine `path`; empty when nothing risky was found.
+pub fn assess(path: &Path) -> Vec<String> {
+    let mut reasons = Vec::new();
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    check_name(&name, &mut reasons);
+    check_content(path, &ext, &mut reasons);
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::SimpleFileOptions;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-safety-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn name_reasons(name: &str) -> Vec<String> {
+        let mut reasons = Vec::new();
+        check_name(name, &mut reasons);
+        reasons
+    }
+
+    fn content_reasons(dir: &Path, name: &str, bytes: &[u8]) -> Vec<String> {
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        let ext = path.extension().unwrap().to_string_lossy().to_ascii_lowercase();
+        let mut reasons = Vec::new();
+        check_content(&path, &ext, &mut reasons);
+        reasons
+    }
+
+    fn zip_bytes(dir: &Path, entries: &[&str]) -> Vec<u8> {
+        let path = dir.join("build.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for name in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+        fs::read(&path).unwrap()
+    }
+
+    /// A minimal 64-bit x86_64 PE header.
+    fn pe() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x100];
+        buf[..2].copy_from_slice(b"MZ");
+        buf[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        buf[0x80..0x84].copy_from_slice(b"PE\0\0");
+        buf[0x84..0x86].copy_from_slice(&0x8664u16.to_le_bytes());
+        buf[0x98..0x9A].copy_from_slice(&0x20Bu16.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn names_flag_double_extensions_and_bidi_controls() {
+        assert_eq!(name_reasons("invoice.pdf.exe"), ["double extension .pdf.exe"]);
+        assert_eq!(name_reasons("Photo.JPG .scr"), ["double extension .jpg.scr"]);
+        assert_eq!(name_reasons("notes.docx.js"), ["double extension .docx.js"]);
+        assert_eq!(name_reasons("invoice\u{202E}fdp.exe"), ["name contains a right-to-left override character"]);
+        assert_eq!(escape_bidi("a\u{202E}b\u{2066}c"), "a<U+202E>b<U+2066>c");
+
+        for fine in ["setup.exe", "report.v2.pdf", "backup.tar.gz", "photo.jpg.txt", "run.sh.exe", "plain"] {
+            assert!(name_reasons(fine).is_empty(), "{}", fine);
+        }
+    }
+
+    #[test]
+    fn executables_with_document_extensions_are_flagged() {
+        let dir = scratch("disguised");
+        let reasons = content_reasons(&dir, "invoice.pdf", &pe());
+        assert_eq!(reasons, ["PE 64-bit x86_64 program for Windows disguised as .pdf"]);
+        assert_eq!(content_reasons(&dir, "song.mp3", &pe()).len(), 1);
+
+        // A real PDF, or a PE with an extension that says what it is, is fine
+        assert!(content_reasons(&dir, "invoice.pdf", b"%PDF-1.7\n").is_empty());
+        assert!(content_reasons(&dir, "tool.exe", &pe()).is_empty());
+        assert!(content_reasons(&dir, "tool.bin", &pe()).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn macro_documents_are_flagged() {
+        let dir = scratch("macros");
+        let macro_zip = zip_bytes(&dir, &["[Content_Types].xml", "word/document.xml", "word/vbaProject.bin"]);
+        assert_eq!(content_reasons(&dir, "report.docm", &macro_zip), ["Office document contains VBA macros"]);
+        assert_eq!(content_reasons(&dir, "sheet.XLSX", &macro_zip).len(), 1);
+        let plain_zip = zip_bytes(&dir, &["[Content_Types].xml", "word/document.xml"]);
+        assert!(content_reasons(&dir, "report.docx", &plain_zip).is_empty());
+        // Only Office extensions are opened as OOXML
+        assert!(content_reasons(&dir, "bundle.zip", &macro_zip).is_empty());
+
+        let mut ole = OLE_MAGIC.to_vec();
+        ole.extend(vec![0u8; 512]);
+        let clean_ole = ole.clone();
+        ole.extend(utf16le("_VBA_PROJECT"));
+        assert_eq!(content_reasons(&dir, "old.doc", &ole), ["Office document contains VBA macros"]);
+        assert!(content_reasons(&dir, "old.xls", &clean_ole).is_empty());
+        // The stream name alone, outside an OLE container, is not enough
+        assert!(content_reasons(&dir, "fake.doc", &utf16le("_VBA_PROJECT")).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scripted_markup_is_flagged() {
+        let dir = scratch("markup");
+        let page = b"<html><body><SCRIPT src=x.js></SCRIPT></body></html>";
+        assert_eq!(content_reasons(&dir, "page.html", page), ["HTML file with embedded script"]);
+        assert_eq!(content_reasons(&dir, "link.htm", b"<a href=\"javascript:run()\">x</a>").len(), 1);
+        assert_eq!(
+            content_reasons(&dir, "logo.svg", b"<svg xmlns=\"http://www.w3.org/2000/svg\" onload=\"alert(1)\"/>"),
+            ["SVG file with embedded script"]
+        );
+
+        assert!(content_reasons(&dir, "page.html", b"<html><body><p>scripts are fun</p></body></html>").is_empty());
+        assert!(content_reasons(&dir, "logo.svg", b"<svg><circle r=\"4\"/></svg>").is_empty());
+        // Event handlers only count in SVG; in HTML they come with a <script> anyway
+        assert!(content_reasons(&dir, "page.html", b"<body onload=\"x()\">").is_empty());
+        // A script in a file that is not markup is just text
+        assert!(content_reasons(&dir, "notes.txt", page).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn assess_combines_name_and_content() {
+        let dir = scratch("assess");
+        let path = dir.join("cv.pdf.exe");
+        fs::write(&path, pe()).unwrap();
+        assert_eq!(assess(&path), ["double extension .pdf.exe"]);
+
+        let path = dir.join("cv\u{202E}fdp.pdf");
+        fs::write(&path, pe()).unwrap();
+        assert_eq!(assess(&path).len(), 2);
+
+        assert!(assess(&dir.join("missing.pdf")).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
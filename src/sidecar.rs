@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::SidecarConfig;
+
+const VIDEO_EXTS: &[&str] = &["mkv", "mp4", "m4v", "avi", "mov", "webm", "wmv", "mpg", "mpeg", "ts", "ogv", "flv"];
+const LOSSLESS_AUDIO_EXTS: &[&str] = &["flac", "ape", "wav", "wv", "aiff", "alac", "dsf"];
+const RAW_FIRST: &[&str] = &["cr2", "cr3", "nef", "arw", "dng", "raf", "orf", "rw2", "raw", "jpg", "jpeg", "heic", "tif", "tiff", "*"];
+
+/// Sidecar extension -> primary extensions it may attach to, in order of preference.
+/// `*` matches any primary.
+#[derive(Debug, Clone)]
+pub struct SidecarRules {
+    rules: HashMap<String, Vec<String>>,
+}
+
+fn owned(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+impl Default for SidecarRules {
+    fn default() -> Self {
+        let mut rules = HashMap::new();
+        for ext in ["srt", "ass", "ssa", "sub", "idx", "vtt", "nfo", "thm"] {
+            rules.insert(ext.to_string(), owned(VIDEO_EXTS));
+        }
+        for ext in ["cue", "log"] {
+            rules.insert(ext.to_string(), owned(LOSSLESS_AUDIO_EXTS));
+        }
+        rules.insert("xmp".to_string(), owned(RAW_FIRST));
+        rules.insert("aae".to_string(), owned(&["heic", "jpg", "jpeg", "png", "mov"]));
+        Self { rules }
+    }
+}
+
+impl SidecarRules {
+    /// Built-in rules with the config's entries added or replaced; `None` when disabled.
+    pub fn from_config(config: &SidecarConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let mut rules = Self::default();
+        for (sidecar, primaries) in &config.rules {
+            let primaries: Vec<String> = primaries.iter().map(|p| p.to_ascii_lowercase()).collect();
+            if primaries.is_empty() {
+                rules.rules.remove(&sidecar.to_ascii_lowercase());
+            } else {
+                rules.rules.insert(sidecar.to_ascii_lowercase(), primaries);
+            }
+        }
+        Some(rules)
+    }
+
+    fn primaries_for(&self, path: &Path) -> Option<&[String]> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        self.rules.get(&ext).map(Vec::as_slice)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// `movie.srt`, `movie.en.srt` and `movie.mkv.nfo` all belong to `movie.mkv`.
+fn belongs_to(sidecar: &Path, primary: &Path) -> bool {
+    let sidecar_stem = stem(sidecar).to_lowercase();
+    let primary_stem = stem(primary).to_lowercase();
+    sidecar_stem == primary_stem
+        || sidecar_stem == file_name(primary).to_lowercase()
+        || sidecar_stem.starts_with(&format!("{}.", primary_stem))
+}
+
+fn primary_rank(primary: &Path, allowed: &[String]) -> Option<usize> {
+    let ext = primary.extension()?.to_str()?.to_ascii_lowercase();
+    allowed.iter().position(|a| *a == ext || a == "*")
+}
+
+/// Splits `entries` into files to sort on their own and sidecars keyed by the primary they follow.
+pub fn group(entries: Vec<PathBuf>, rules: &SidecarRules) -> (Vec<PathBuf>, HashMap<PathBuf, Vec<PathBuf>>) {
+    let (candidates, mut primaries): (Vec<PathBuf>, Vec<PathBuf>) =
+        entries.into_iter().partition(|p| rules.primaries_for(p).is_some());
+    primaries.sort();
+
+    let mut sidecars: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut unmatched = Vec::new();
+    for candidate in candidates {
+        let allowed = rules.primaries_for(&candidate).unwrap_or_default();
+        let best = primaries
+            .iter()
+            .filter(|p| p.parent() == candidate.parent() && belongs_to(&candidate, p))
+            .filter_map(|p| primary_rank(p, allowed).map(|rank| (rank, p)))
+            // `movie.part2.srt` goes with `movie.part2.mkv` rather than `movie.mkv`
+            .min_by_key(|(rank, p)| (*rank, Reverse(stem(p).len())));
+
+        match best {
+            Some((_, primary)) => sidecars.entry(primary.clone()).or_default().push(candidate),
+            None => unmatched.push(candidate),
+        }
+    }
+
+    primaries.extend(unmatched);
+    (primaries, sidecars)
+}
+
+/// New name for a sidecar after its primary was renamed: `movie.en.srt` + `Movie (2020).mkv`
+/// -> `Movie (2020).en.srt`.
+pub fn follow_name(sidecar: &Path, primary: &Path, new_primary: &Path) -> PathBuf {
+    let name = file_name(sidecar);
+    let old_stem = stem(primary);
+    let new_stem = stem(new_primary);
+
+    match (name.get(..old_stem.len()), name.get(old_stem.len()..)) {
+        (Some(prefix), Some(suffix)) if prefix.to_lowercase() == old_stem.to_lowercase() => {
+            PathBuf::from(format!("{}{}", new_stem, suffix))
+        }
+        _ => PathBuf::from(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    fn sidecars_of<'a>(groups: &'a HashMap<PathBuf, Vec<PathBuf>>, primary: &str) -> Vec<&'a str> {
+        let mut found: Vec<&str> = groups
+            .get(Path::new(primary))
+            .map(|list| list.iter().filter_map(|p| p.to_str()).collect())
+            .unwrap_or_default();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn sidecars_match_by_stem_name_or_prefix() {
+        let movie = Path::new("movie.mkv");
+        assert!(belongs_to(Path::new("movie.srt"), movie));
+        assert!(belongs_to(Path::new("Movie.EN.srt"), movie));
+        assert!(belongs_to(Path::new("movie.mkv.nfo"), movie));
+        assert!(!belongs_to(Path::new("movie2.srt"), movie));
+        assert!(!belongs_to(Path::new("movies.srt"), movie));
+        assert!(!belongs_to(Path::new("mov.srt"), movie));
+    }
+
+    #[test]
+    fn sidecars_follow_a_renamed_primary() {
+        let follow = |sidecar: &str, primary: &str, renamed: &str| {
+            follow_name(Path::new(sidecar), Path::new(primary), Path::new(renamed))
+        };
+        assert_eq!(follow("movie.en.srt", "movie.mkv", "Movie (2020).mkv"), Path::new("Movie (2020).en.srt"));
+        assert_eq!(follow("MOVIE.mkv.nfo", "movie.mkv", "Movie (2020).mkv"), Path::new("Movie (2020).mkv.nfo"));
+        assert_eq!(follow("IMG_1.xmp", "IMG_1.CR2", "IMG_1_1.CR2"), Path::new("IMG_1_1.xmp"));
+        assert_eq!(follow("é.srt", "ab.mkv", "cd.mkv"), Path::new("é.srt"));
+    }
+
+    #[test]
+    fn sidecars_pick_the_preferred_primary() {
+        let (files, groups) = group(
+            paths(&[
+                "movie.mkv", "movie.en.srt", "movie.part2.mkv", "movie.part2.srt", "orphan.srt",
+                "IMG_1.jpg", "IMG_1.CR2", "IMG_1.xmp", "album.flac", "album.cue", "other/movie.nfo",
+            ]),
+            &SidecarRules::default(),
+        );
+
+        assert_eq!(sidecars_of(&groups, "movie.mkv"), ["movie.en.srt"]);
+        assert_eq!(sidecars_of(&groups, "movie.part2.mkv"), ["movie.part2.srt"]);
+        assert_eq!(sidecars_of(&groups, "IMG_1.CR2"), ["IMG_1.xmp"]);
+        assert_eq!(sidecars_of(&groups, "album.flac"), ["album.cue"]);
+        assert!(files.contains(&PathBuf::from("orphan.srt")));
+        assert!(files.contains(&PathBuf::from("other/movie.nfo")));
+        assert_eq!(files.len() + groups.values().map(Vec::len).sum::<usize>(), 11);
+    }
+
+    #[test]
+    fn config_adds_replaces_and_removes_rules() {
+        let disabled = SidecarConfig { enabled: false, rules: BTreeMap::new() };
+        assert!(SidecarRules::from_config(&disabled).is_none());
+
+        let config = SidecarConfig {
+            enabled: true,
+            rules: BTreeMap::from([
+                ("SRT".to_string(), Vec::new()),
+                ("txt".to_string(), vec!["PDF".to_string()]),
+            ]),
+        };
+        let rules = SidecarRules::from_config(&config).unwrap();
+        assert!(rules.primaries_for(Path::new("a.srt")).is_none());
+        assert_eq!(rules.primaries_for(Path::new("a.TXT")), Some(&["pdf".to_string()][..]));
+        assert!(rules.primaries_for(Path::new("a.xmp")).is_some());
+    }
+}
@@ -0,0 +1,360 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::paths::config_dir;
+
+pub const DEFAULT_PRIORITY: i32 = 50;
+/// Never read more than this much of a file, whatever offsets a user entry asks for.
+const MAX_HEADER: usize = 64 * 1024;
+
+static DB: OnceLock<SignatureDb> = OnceLock::new();
+
+/// One magic-number test: `bytes` (under `mask`) starting at any offset in `offsets`.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub offsets: (usize, usize),
+    pub bytes: Vec<u8>,
+    pub mask: Option<Vec<u8>>,
+}
+
+impl Rule {
+    fn at(offset: usize, bytes: &[u8]) -> Self {
+        Self { offsets: (offset, offset), bytes: bytes.to_vec(), mask: None }
+    }
+
+    fn matches_at(&self, buf: &[u8], at: usize) -> bool {
+        let Some(window) = buf.get(at..at + self.bytes.len()) else {
+            return false;
+        };
+        window.iter().zip(&self.bytes).enumerate().all(|(i, (&b, &want))| {
+            let mask = self.mask.as_ref().and_then(|m| m.get(i)).copied().unwrap_or(0xFF);
+            b & mask == want & mask
+        })
+    }
+
+    /// Offset the rule matched at, if any.
+    pub fn find(&self, buf: &[u8]) -> Option<usize> {
+        (self.offsets.0..=self.offsets.1).find(|&at| self.matches_at(buf, at))
+    }
+
+    fn reach(&self) -> usize {
+        self.offsets.1 + self.bytes.len()
+    }
+}
+
+impl fmt::Display for Rule {
+    /// e.g. `89 50 4E 47 @0`, `66 74 79 70 @4..8 mask FF FF FF FF`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex(&self.bytes))?;
+        match self.offsets {
+            (a, b) if a == b => write!(f, " @{}", a)?,
+            (a, b) => write!(f, " @{}..{}", a, b)?,
+        }
+        if let Some(mask) = &self.mask {
+            write!(f, " mask {}", hex(mask))?;
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    Builtin,
+    File(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Builtin => write!(f, "built-in"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A file type recognised when every rule matches.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub name: String,
+    pub ext: String,
+    pub priority: i32,
+    /// Executable formats: they trigger the binary-file prompt but never change the extension
+    pub binary: bool,
+    pub rules: Vec<Rule>,
+    pub source: Source,
+}
+
+impl Signature {
+    /// Offsets each rule matched at, or `None` if any rule fails.
+    pub fn matches(&self, buf: &[u8]) -> Option<Vec<usize>> {
+        self.rules.iter().map(|r| r.find(buf)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDb {
+    pub entries: Vec<Signature>,
+}
+
+fn builtin(name: &str, ext: &str, priority: i32, binary: bool, rules: &[(usize, &[u8])]) -> Signature {
+    Signature {
+        name: name.to_string(),
+        ext: ext.to_string(),
+        priority,
+        binary,
+        rules: rules.iter().map(|(offset, bytes)| Rule::at(*offset, bytes)).collect(),
+        source: Source::Builtin,
+    }
+}
+
+impl SignatureDb {
+    pub fn builtin() -> Self {
+        let p = DEFAULT_PRIORITY;
+        let entries = vec![
+            builtin("PNG image", "png", p, false, &[(0, b"\x89PNG\r\n\x1A\n")]),
+            builtin("JPEG image", "jpg", p, false, &[(0, b"\xFF\xD8\xFF")]),
+            builtin("GIF image (87a)", "gif", p, false, &[(0, b"GIF87a")]),
+            builtin("GIF image (89a)", "gif", p, false, &[(0, b"GIF89a")]),
+            builtin("BMP image", "bmp", p - 10, false, &[(0, b"BM")]),
+            builtin("PDF document", "pdf", p, false, &[(0, b"%PDF")]),
+            builtin("ZIP archive", "zip", p, false, &[(0, b"PK\x03\x04")]),
+            builtin("gzip stream", "gz", p, false, &[(0, b"\x1F\x8B\x08")]),
+            builtin("Matroska / WebM", "mkv", p, false, &[(0, b"\x1A\x45\xDF\xA3")]),
+            builtin("RIFF WebP image", "webp", p, false, &[(0, b"RIFF"), (8, b"WEBP")]),
+            builtin("RIFF WAVE audio", "wav", p, false, &[(0, b"RIFF"), (8, b"WAVE")]),
+            builtin("RIFF AVI video", "avi", p, false, &[(0, b"RIFF"), (8, b"AVI ")]),
+            builtin("ISO BMFF (MP4)", "mp4", p - 10, false, &[(4, b"ftyp")]),
+            builtin("MPEG-4 video (M4V)", "m4v", p, false, &[(4, b"ftypM4V ")]),
+            builtin("MPEG-4 audio (M4A)", "m4a", p, false, &[(4, b"ftypM4A ")]),
+            builtin("MPEG-4 audiobook (M4B)", "m4b", p, false, &[(4, b"ftypM4B ")]),
+            builtin("QuickTime movie", "mov", p, false, &[(4, b"ftypqt  ")]),
+            builtin("Windows PE / DOS executable", "exe", p, true, &[(0, b"MZ")]),
+            builtin("ELF executable", "elf", p, true, &[(0, b"\x7FELF")]),
+            builtin("Mach-O universal binary", "mach-o", p - 10, true, &[(0, b"\xCA\xFE\xBA\xBE")]),
+            builtin("Mach-O 64-bit (LE)", "mach-o", p, true, &[(0, b"\xCF\xFA\xED\xFE")]),
+            builtin("Mach-O 64-bit (BE)", "mach-o", p, true, &[(0, b"\xFE\xED\xFA\xCF")]),
+            builtin("Mach-O 32-bit (BE)", "mach-o", p, true, &[(0, b"\xFE\xED\xFA\xCE")]),
+            builtin("WebAssembly module", "wasm", p, true, &[(0, b"\x00asm")]),
+        ];
+        Self { entries }
+    }
+
+    /// Built-in entries plus those from `path`; a file entry with the same name replaces a built-in.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut db = Self::builtin();
+        let path = match path {
+            Some(p) => p.to_path_buf(),
+            None => match config_dir().map(|d| d.join("signatures.toml")) {
+                Some(p) if p.is_file() => p,
+                _ => return Ok(db),
+            },
+        };
+
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read signature file {}", path.display()))?;
+        let file: SignatureFile =
+            toml::from_str(&text).with_context(|| format!("invalid signature file {}", path.display()))?;
+
+        for raw in file.signature {
+            let entry = raw
+                .into_signature(&path)
+                .with_context(|| format!("invalid signature file {}", path.display()))?;
+            db.entries.retain(|e| e.name != entry.name);
+            db.entries.push(entry);
+        }
+        Ok(db)
+    }
+
+    /// How many leading bytes matching needs to look at.
+    pub fn header_len(&self) -> usize {
+        self.entries
+            .iter()
+            .flat_map(|e| &e.rules)
+            .map(Rule::reach)
+            .max()
+            .unwrap_or(0)
+            .min(MAX_HEADER)
+    }
+
+    /// Every matching entry, best first: higher priority, then file entries before built-ins.
+    pub fn matches<'a>(&'a self, buf: &[u8]) -> Vec<(&'a Signature, Vec<usize>)> {
+        let mut found: Vec<(usize, &Signature, Vec<usize>)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.matches(buf).map(|offsets| (i, e, offsets)))
+            .collect();
+        found.sort_by_key(|(i, e, _)| (std::cmp::Reverse(e.priority), std::cmp::Reverse(*i)));
+        found.into_iter().map(|(_, e, offsets)| (e, offsets)).collect()
+    }
+
+    /// The entry that decides the file's extension.
+    pub fn best(&self, buf: &[u8]) -> Option<&Signature> {
+        self.matches(buf).into_iter().map(|(e, _)| e).find(|e| !e.binary)
+    }
+
+    pub fn is_binary(&self, buf: &[u8]) -> bool {
+        self.entries.iter().any(|e| e.binary && e.matches(buf).is_some())
+    }
+}
+
+/// Sets the database used for detection; later calls are ignored.
+pub fn init(db: SignatureDb) {
+    let _ = DB.set(db);
+}
+
+/// The database set by `init`, or the built-in entries.
+pub fn db() -> &'static SignatureDb {
+    DB.get_or_init(SignatureDb::builtin)
+}
+
+/// `signatures.toml`:
+///
+/// ```toml
+/// [[signature]]
+/// name = "Zstandard frame"
+/// ext = "zst"
+/// priority = 60            # optional, default 50
+/// binary = false           # optional; true only flags the file for the binary prompt
+/// [[signature.match]]
+/// offset = 0               # or "0..512" to search a range of start offsets
+/// hex = "28 B5 2F FD"      # or text = "..."
+/// mask = "FF FF FF FF"     # optional, same length as the pattern
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureFile {
+    #[serde(default)]
+    signature: Vec<RawSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSignature {
+    name: String,
+    ext: String,
+    #[serde(default)]
+    priority: Option<i32>,
+    #[serde(default)]
+    binary: bool,
+    #[serde(rename = "match")]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawOffset {
+    At(usize),
+    Range(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    #[serde(default)]
+    offset: Option<RawOffset>,
+    hex: Option<String>,
+    text: Option<String>,
+    mask: Option<String>,
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        bail!("invalid hex string '{}'", s);
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).with_context(|| format!("invalid hex string '{}'", s)))
+        .collect()
+}
+
+impl RawRule {
+    fn into_rule(self) -> Result<Rule> {
+        let bytes = match (self.hex, self.text) {
+            (Some(hex), None) => parse_hex(&hex)?,
+            (None, Some(text)) if !text.is_empty() => text.into_bytes(),
+            _ => bail!("each match needs exactly one of `hex` or `text`"),
+        };
+        let mask = self.mask.as_deref().map(parse_hex).transpose()?;
+        if mask.as_ref().is_some_and(|m| m.len() != bytes.len()) {
+            bail!("mask length differs from the pattern length");
+        }
+        let offsets = match self.offset {
+            None => (0, 0),
+            Some(RawOffset::At(at)) => (at, at),
+            Some(RawOffset::Range(range)) => {
+                let (a, b) = range
+                    .split_once("..")
+                    .with_context(|| format!("invalid offset range '{}', expected START..END", range))?;
+                let (a, b): (usize, usize) = (a.trim().parse()?, b.trim().parse()?);
+                if a > b {
+                    bail!("offset range '{}' ends before it starts", range);
+                }
+                (a, b)
+            }
+        };
+        if offsets.1.checked_add(bytes.len()).is_none_or(|end| end > MAX_HEADER) {
+            bail!("pattern reaches past the first {} bytes of the file", MAX_HEADER);
+        }
+        Ok(Rule { offsets, bytes, mask })
+    }
+}
+
+impl RawSignature {
+    fn into_signature(self, path: &Path) -> Result<Signature> {
+        if self.rules.is_empty() {
+            bail!("signature '{}' has no [[signature.match]] rules", self.name);
+        }
+        let rules = self
+            .rules
+            .into_iter()
+            .map(RawRule::into_rule)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("in signature '{}'", self.name))?;
+        Ok(Signature {
+            ext: self.ext.trim_start_matches('.').to_ascii_lowercase(),
+            name: self.name,
+            priority: self.priority.unwrap_or(DEFAULT_PRIORITY),
+            binary: self.binary,
+            rules,
+            source: Source::File(path.to_path_buf()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_str(toml: &str) -> Result<SignatureDb> {
+        let dir = std::env::temp_dir().join(format!("sortify-sig-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{:x}.toml", toml.len()));
+        fs::write(&path, toml).unwrap();
+        let db = SignatureDb::load(Some(&path));
+        fs::remove_file(&path).ok();
+        db
+    }
+
+    #[test]
+    fn offsets_past_the_header_are_rejected() {
+        let ok = "[[signature]]\nname = \"x\"\next = \"x\"\n[[signature.match]]\noffset = \"0..8\"\ntext = \"XY\"\n";
+        assert!(load_str(ok).unwrap().entries.iter().any(|e| e.name == "x"));
+
+        let far = ok.replace("0..8", "0..65535");
+        assert!(load_str(&far).is_err());
+
+        let huge = ok.replace("0..8", &format!("0..{}", usize::MAX));
+        let err = load_str(&huge).unwrap_err();
+        assert!(format!("{:#}", err).contains("invalid signature file"));
+    }
+}
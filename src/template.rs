@@ -0,0 +1,203 @@
+use anyhow::{Result, bail};
+use std::path::PathBuf;
+
+use crate::metadata::Metadata;
+
+const MAX_COMPONENT: usize = 120;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Field { key: String, width: Option<usize> },
+    /// `[...]` is dropped entirely when any field inside it is missing.
+    Optional(Vec<Segment>),
+}
+
+/// Destination layout such as `{artist}/{album}/[{track:02} - ]{title}.{ext}`.
+#[derive(Debug, Clone)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+fn parse_segments(src: &str, chars: &mut std::str::Chars, nested: bool) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut field = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    field.push(c);
+                }
+                if !closed {
+                    return Err(format!("unclosed '{{' in template '{}'", src));
+                }
+                let (key, width) = match field.split_once(':') {
+                    Some((k, w)) => {
+                        let width = w
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid width '{}' in template '{}'", w, src))?;
+                        (k, Some(width))
+                    }
+                    None => (field.as_str(), None),
+                };
+                if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!("invalid field '{{{}}}' in template '{}'", field, src));
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field { key: key.to_string(), width });
+            }
+            '[' if !nested => {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Optional(parse_segments(src, chars, true)?));
+            }
+            ']' if nested => {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(literal));
+                }
+                return Ok(segments);
+            }
+            _ => literal.push(c),
+        }
+    }
+
+    if nested {
+        return Err(format!("unclosed '[' in template '{}'", src));
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// Makes a metadata value safe to use as (part of) a single path component.
+pub fn sanitize_component(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_COMPONENT)
+        .collect();
+
+    let trimmed = cleaned.trim().trim_end_matches('.').trim();
+    match trimmed {
+        "" | "." | ".." => "_".to_string(),
+        s => s.to_string(),
+    }
+}
+
+fn render_segments(segments: &[Segment], meta: &Metadata, out: &mut String) -> Result<(), String> {
+    for segment in segments {
+        match segment {
+            Segment::Literal(s) => out.push_str(s),
+            Segment::Field { key, width } => {
+                let value = meta.get(key).ok_or_else(|| key.clone())?;
+                let value = sanitize_component(value);
+                match (width, value.parse::<u64>()) {
+                    (Some(w), Ok(n)) => out.push_str(&format!("{:0w$}", n, w = *w)),
+                    _ => out.push_str(&value),
+                }
+            }
+            Segment::Optional(inner) => {
+                let mut buf = String::new();
+                if render_segments(inner, meta, &mut buf).is_ok() {
+                    out.push_str(&buf);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Template {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let segments = parse_segments(src, &mut src.chars(), false)?;
+        if segments.is_empty() {
+            return Err("empty template".to_string());
+        }
+        Ok(Self { segments })
+    }
+
+    /// Renders a relative path; fails if a required field is missing.
+    pub fn render(&self, meta: &Metadata) -> Result<PathBuf> {
+        let mut out = String::new();
+        if let Err(key) = render_segments(&self.segments, meta, &mut out) {
+            bail!("template field '{{{}}}' has no value", key);
+        }
+
+        let path: PathBuf = out
+            .split('/')
+            .map(str::trim)
+            .filter(|c| !c.is_empty() && *c != ".")
+            .map(|c| if c == ".." { "_" } else { c })
+            .collect();
+
+        if path.as_os_str().is_empty() {
+            bail!("template rendered an empty path");
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::DEFAULT_AUDIO_LAYOUT;
+    use std::path::Path;
+
+    fn meta(pairs: &[(&str, &str)]) -> Metadata {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn render(template: &str, pairs: &[(&str, &str)]) -> Result<PathBuf> {
+        Template::parse(template).unwrap().render(&meta(pairs))
+    }
+
+    #[test]
+    fn optional_groups_drop_out_when_a_field_is_missing() {
+        let song = [("artist", "Artist"), ("album", "Album"), ("title", "Title"), ("ext", "mp3")];
+        let mut numbered = song.to_vec();
+        numbered.push(("track", "3"));
+
+        assert_eq!(render(DEFAULT_AUDIO_LAYOUT, &numbered).unwrap(), Path::new("Artist/Album/03 - Title.mp3"));
+        assert_eq!(render(DEFAULT_AUDIO_LAYOUT, &song).unwrap(), Path::new("Artist/Album/Title.mp3"));
+        assert_eq!(render("{track:03}", &[("track", "B-side")]).unwrap(), Path::new("B-side"));
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        for bad in ["", "{}", "{artist", "{a-b}", "{track:x}", "[{artist}", "{artist}/[{album}"] {
+            assert!(Template::parse(bad).is_err(), "{:?}", bad);
+        }
+        assert!(Template::parse("plain/folder").is_ok());
+    }
+
+    #[test]
+    fn missing_fields_and_empty_paths_fail_to_render() {
+        let err = render("{artist}/{album}", &[("artist", "A")]).unwrap_err();
+        assert!(err.to_string().contains("{album}"));
+        assert!(render("[{album}]", &[]).is_err());
+    }
+
+    #[test]
+    fn values_cannot_escape_their_component() {
+        assert_eq!(render("{artist}/{album}", &[("artist", "AC/DC"), ("album", "..")]).unwrap(), Path::new("AC_DC/_"));
+        assert_eq!(render("../{a}/./", &[("a", "x")]).unwrap(), Path::new("_/x"));
+        assert_eq!(sanitize_component(" what? . "), "what_");
+        assert_eq!(sanitize_component("\u{0}"), "_");
+        assert_eq!(sanitize_component(&"x".repeat(500)).len(), MAX_COMPONENT);
+    }
+}
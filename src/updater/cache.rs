@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::paths::cache_dir;
+use crate::updater::github::UpdateRelease;
+use crate::updater::settings::Channel;
+
+#[derive(Serialize, Deserialize)]
+struct CachedCheck {
+    checked_at: u64,
+    source: String,
+    channel: Channel,
+    releases: Vec<UpdateRelease>,
+}
+
+fn cache_file() -> Option<PathBuf> {
+    cache_dir().map(|d| d.join("update-check.json"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Returns the cached releases if the last check for this source and channel is recent enough.
+pub fn load(source: &str, channel: Channel, interval: Duration) -> Option<Vec<UpdateRelease>> {
+    let text = fs::read_to_string(cache_file()?).ok()?;
+    let cached: CachedCheck = serde_json::from_str(&text).ok()?;
+
+    let fresh = now_secs().saturating_sub(cached.checked_at) < interval.as_secs();
+    (fresh && cached.source == source && cached.channel == channel).then_some(cached.releases)
+}
+
+/// Failing to write the cache only means the next run checks again.
+pub fn store(source: &str, channel: Channel, releases: &[UpdateRelease]) {
+    let Some(path) = cache_file() else { return };
+    let record = CachedCheck {
+        checked_at: now_secs(),
+        source: source.to_string(),
+        channel,
+        releases: releases.to_vec(),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    if let Ok(json) = serde_json::to_string(&record) {
+        fs::write(path, json).ok();
+    }
+}
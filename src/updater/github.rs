@@ -1,40 +1,165 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::blocking::Client;
 use reqwest::header::USER_AGENT;
 use semver::Version;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fs;
 
-use crate::updater::platform_check::target_suffix;
+use crate::updater::cache;
+use crate::updater::platform_check::{AssetKind, Target, expand_asset_pattern};
+use crate::updater::settings::{Channel, UpdateSettings, UpdateSource};
 
-const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PAGE_SIZE: usize = 100;
+const MAX_PAGES: usize = 20;
+const CHANGELOG_LINES: usize = 20;
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UpdateRelease {
     pub tag_name: String,
     pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub body: Option<String>,
     pub assets: Vec<UpdateAsset>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UpdateAsset {
     pub name: String,
     pub browser_download_url: String,
 }
 
-fn find_asset_url(release: &UpdateRelease) -> Option<String> {
-    let suffix = target_suffix();
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReleaseFile {
+    Many(Vec<UpdateRelease>),
+    One(UpdateRelease),
+}
+
+impl UpdateRelease {
+    pub fn version(&self) -> Option<Version> {
+        parse_tag(&self.tag_name)
+    }
+}
+
+/// Picks the asset for this platform: an exact match of the configured pattern if there is one,
+/// otherwise the best-scoring name by OS, architecture and libc.
+pub fn find_asset<'a>(release: &'a UpdateRelease, pattern: Option<&str>) -> Option<&'a UpdateAsset> {
+    let target = Target::current();
+
+    if let Some(pattern) = pattern {
+        return [AssetKind::Binary, AssetKind::TarGz, AssetKind::Zip]
+            .iter()
+            .map(|&kind| expand_asset_pattern(pattern, &release.tag_name, &target, kind))
+            .find_map(|name| release.assets.iter().find(|a| a.name == name));
+    }
+
     release
         .assets
         .iter()
-        .find(|a| a.name.ends_with(&suffix))
-        .map(|a| a.browser_download_url.clone())
+        .filter_map(|a| target.score(&a.name).map(|score| (score, a)))
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, a)| a)
+}
+
+/// Parses tags like `v1.2.3`, `sortify-1.2` or `1.3.0-beta.1`; returns `None` for anything else.
+pub fn parse_tag(tag: &str) -> Option<Version> {
+    let start = tag.find(|c: char| c.is_ascii_digit())?;
+    let raw = &tag[start..];
+    if let Ok(v) = Version::parse(raw) {
+        return Some(v);
+    }
+
+    // Pad missing minor/patch components: "1.2-rc1" -> "1.2.0-rc1"
+    let (core, rest) = match raw.find(['-', '+']) {
+        Some(i) => raw.split_at(i),
+        None => (raw, ""),
+    };
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|p| p.parse::<u64>().is_err()) {
+        return None;
+    }
+    parts.resize(3, "0");
+    Version::parse(&format!("{}{}", parts.join("."), rest)).ok()
+}
+
+fn fetch_page(client: &Client, url: &str) -> Result<Vec<UpdateRelease>> {
+    client
+        .get(url)
+        .header(USER_AGENT, "sortify-updater")
+        .send()
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("cannot reach {}", url))?
+        .json::<Vec<UpdateRelease>>()
+        .context("invalid release list")
+}
+
+/// Fetches every non-draft release from the configured source.
+pub fn fetch_releases(client: &Client, settings: &UpdateSettings) -> Result<Vec<UpdateRelease>> {
+    let base = match &settings.source {
+        UpdateSource::File(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("cannot read update source {}", path.display()))?;
+            let parsed: ReleaseFile = serde_json::from_str(&text)
+                .with_context(|| format!("invalid release data in {}", path.display()))?;
+            let releases = match parsed {
+                ReleaseFile::Many(list) => list,
+                ReleaseFile::One(r) => vec![r],
+            };
+            return Ok(releases.into_iter().filter(|r| !r.draft).collect());
+        }
+        UpdateSource::Api(base) => base,
+    };
+
+    let mut releases = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let url = format!("{}/releases?per_page={}&page={}", base, PAGE_SIZE, page);
+        let batch = fetch_page(client, &url)?;
+        let last = batch.len() < PAGE_SIZE;
+        releases.extend(batch.into_iter().filter(|r| !r.draft));
+        if last {
+            break;
+        }
+    }
+
+    Ok(releases)
+}
+
+/// Releases newer than the running version that the channel allows, highest first.
+pub fn select_updates(releases: Vec<UpdateRelease>, channel: Channel) -> Vec<UpdateRelease> {
+    let Some(current) = parse_tag(CURRENT_VERSION) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<(Version, UpdateRelease)> = releases
+        .into_iter()
+        .filter_map(|r| r.version().map(|v| (v, r)))
+        .filter(|(v, r)| v > &current && channel.allows(v, r.prerelease))
+        .collect();
+
+    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+    candidates.dedup_by(|a, b| a.0 == b.0);
+    candidates.into_iter().map(|(_, r)| r).collect()
 }
 
-pub fn check_for_updates(include_prerelease: bool) -> Result<Option<UpdateRelease>> {
-    println!("{}", "[ Sortify Updater ]".bright_cyan().bold());
-    println!("{}", "→ Checking for updates...".dimmed());
+pub fn check_for_updates(settings: &UpdateSettings) -> Result<Option<UpdateRelease>> {
+    let source_key = settings.source.cache_key();
+
+    if let Some(cached) = cache::load(&source_key, settings.channel, settings.interval) {
+        let updates = select_updates(cached, settings.channel);
+        if updates.is_empty() {
+            return Ok(None);
+        }
+        eprintln!("{}", "[ Sortify Updater ]".bright_cyan().bold());
+        return handle_updates(updates, settings);
+    }
+
+    eprintln!("{}", "[ Sortify Updater ]".bright_cyan().bold());
+    eprintln!("{}", "→ Checking for updates...".dimmed());
 
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -44,86 +169,135 @@ pub fn check_for_updates(include_prerelease: bool) -> Result<Option<UpdateReleas
             .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ "),
     );
     pb.enable_steady_tick(std::time::Duration::from_millis(100));
-    pb.set_message("Contacting GitHub...");
+    pb.set_message("Contacting update server...");
 
-    let client = Client::new();
+    let updates = settings
+        .client()
+        .and_then(|client| fetch_releases(&client, settings))
+        .map(|releases| select_updates(releases, settings.channel));
 
-    let url = [
-        "https://api.github.com/repos/OctoBanon-Main/sortify/releases",
-        "https://api.github.com/repos/OctoBanon-Main/sortify/releases/latest",
-    ]
-    .iter()
-    .find(|_| include_prerelease)
-    .unwrap_or(&"https://api.github.com/repos/OctoBanon-Main/sortify/releases/latest");
+    pb.finish_and_clear();
 
-    let response = client
-        .get(*url)
-        .header(USER_AGENT, "sortify-updater")
-        .send();
-
-    let parsed = response
-        .ok()
-        .and_then(|r| r.error_for_status().ok())
-        .map(|resp| {
-            if include_prerelease {
-                resp.json::<Vec<UpdateRelease>>().ok().and_then(|mut releases| {
-                    releases
-                        .drain(..)
-                        .filter(|r| include_prerelease || !r.prerelease)
-                        .next()
-                })
-            } else {
-                resp.json::<UpdateRelease>().ok()
-            }
-        })
-        .flatten();
+    // Failed checks are cached as well so offline machines wait a full interval before retrying.
+    cache::store(
+        &source_key,
+        settings.channel,
+        updates.as_deref().unwrap_or_default(),
+    );
 
-    pb.finish_and_clear();
+    updates.and_then(|u| handle_updates(u, settings)).or_else(|err| {
+        eprintln!("{}", format!("Failed to check updates: {:#}", err).red());
+        eprintln!();
+        Ok(None)
+    })
+}
 
-    parsed
-        .map(handle_release)
-        .transpose()
-        .map(|opt| opt.flatten())
-        .or_else(|err| {
-            eprintln!("{}", format!("Failed to check updates: {}", err).red());
-            Ok(None)
-        })
+fn print_changelog(updates: &[UpdateRelease]) {
+    let mut remaining = CHANGELOG_LINES;
+
+    for release in updates {
+        let Some(body) = release.body.as_deref().map(str::trim).filter(|b| !b.is_empty()) else {
+            continue;
+        };
+        if remaining == 0 {
+            eprintln!("  {}", "…".dimmed());
+            break;
+        }
+
+        eprintln!("  {}", release.tag_name.bold());
+        for line in body.lines().filter(|l| !l.trim().is_empty()).take(remaining) {
+            eprintln!("    {}", line.trim_end().dimmed());
+            remaining -= 1;
+        }
+    }
 }
 
-fn handle_release(release: UpdateRelease) -> Result<Option<UpdateRelease>> {
-    let latest = Version::parse(release.tag_name.trim_start_matches('v'))?;
+fn handle_updates(mut updates: Vec<UpdateRelease>, settings: &UpdateSettings) -> Result<Option<UpdateRelease>> {
     let current = Version::parse(CURRENT_VERSION)?;
 
-    match latest.cmp(&current) {
-        std::cmp::Ordering::Less | std::cmp::Ordering::Equal => {
-            println!(
-                "{}",
-                format!("You're using the latest version (v{})", current).green()
-            );
-            println!();
-            Ok(None)
-        }
-        std::cmp::Ordering::Greater => {
-            let label = if release.prerelease {
-                "Pre-release update available!".yellow()
-            } else {
-                "Update available!".yellow()
-            };
+    if updates.is_empty() {
+        eprintln!(
+            "{}",
+            format!("You're using the latest version (v{})", current).green()
+        );
+        eprintln!();
+        return Ok(None);
+    }
+
+    let release = &updates[0];
+    let latest = release.version().context("invalid release tag")?;
+    let label = if release.prerelease || !latest.pre.is_empty() {
+        "Pre-release update available!".yellow()
+    } else {
+        "Update available!".yellow()
+    };
 
-            println!("{}", label);
-            println!("  Current version: v{}", current);
-            println!("  Latest version:  v{}", latest);
+    eprintln!("{}", label);
+    eprintln!("  Current version: v{}", current);
+    eprintln!("  Latest version:  v{}", latest);
 
-            find_asset_url(&release)
-                .map(|url| {
-                    println!("  Download: {}", url);
-                    println!();
-                    println!("{}", "Tip: Run \"sortify --update\" to install the new version.".dimmed());
-                })
-                .unwrap_or_else(|| println!("  No suitable asset found for this platform."));
+    find_asset(release, settings.asset_pattern.as_deref())
+        .map(|asset| eprintln!("  Download: {}", asset.browser_download_url))
+        .unwrap_or_else(|| {
+            eprintln!(
+                "  No suitable asset found for this platform ({}).",
+                Target::current().triple()
+            )
+        });
+
+    if updates.iter().any(|r| r.body.as_deref().is_some_and(|b| !b.trim().is_empty())) {
+        eprintln!();
+        eprintln!("{}", "What's new:".bold());
+        print_changelog(&updates);
+    }
+
+    eprintln!();
+    eprintln!("{}", "Tip: Run \"sortify --update\" to install the new version.".dimmed());
+    eprintln!();
+    Ok(Some(updates.swap_remove(0)))
+}
 
-            println!();
-            Ok(Some(release))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool) -> UpdateRelease {
+        UpdateRelease {
+            tag_name: tag.to_string(),
+            prerelease,
+            draft: false,
+            body: None,
+            assets: Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn tags_parse_with_prefixes_and_short_versions() {
+        assert_eq!(parse_tag("v1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_tag("sortify-1.2"), Some(Version::new(1, 2, 0)));
+        assert_eq!(parse_tag("1.3.0-beta.1"), Version::parse("1.3.0-beta.1").ok());
+        assert_eq!(parse_tag("v1.2-rc1"), Version::parse("1.2.0-rc1").ok());
+        assert_eq!(parse_tag("v1"), None);
+        assert_eq!(parse_tag("v1.2.3.4"), None);
+        assert_eq!(parse_tag("nightly"), None);
+        assert_eq!(parse_tag("1.x"), None);
+    }
+
+    #[test]
+    fn updates_are_newer_allowed_and_highest_first() {
+        let releases = vec![
+            release("v0.0.1", false),
+            release("v99.0.0", false),
+            release("v99.1.0-beta.1", false),
+            release("v99.2.0", true),
+            release("v99.0.0", false),
+            release("garbage", false),
+        ];
+
+        let tags = |channel| -> Vec<String> {
+            select_updates(releases.clone(), channel).into_iter().map(|r| r.tag_name).collect()
+        };
+        assert_eq!(tags(Channel::Stable), ["v99.0.0"]);
+        assert_eq!(tags(Channel::Beta), ["v99.2.0", "v99.1.0-beta.1", "v99.0.0"]);
+    }
+}
@@ -0,0 +1,468 @@
+use anyhow::{Context, Result, bail};
+use colored::*;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use reqwest::header::USER_AGENT;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::output;
+use crate::updater::github::{
+    CURRENT_VERSION, UpdateAsset, UpdateRelease, fetch_releases, find_asset, parse_tag,
+    select_updates,
+};
+use crate::updater::platform_check::{AssetKind, Target};
+use crate::updater::settings::UpdateSettings;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+const BINARY_NAME: &str = if cfg!(windows) { "sortify.exe" } else { "sortify" };
+const CHECKSUM_MANIFESTS: &[&str] = &["SHA256SUMS", "SHA256SUMS.txt", "sha256sums.txt", "checksums.txt"];
+
+/// `None` for http(s) URLs, the path for `file://` URLs and plain paths.
+fn local_path(url: &str) -> Result<Option<&str>> {
+    match url.split_once("://") {
+        None => Ok(Some(url)),
+        Some((scheme, path)) => match scheme.to_ascii_lowercase().as_str() {
+            "http" | "https" => Ok(None),
+            "file" => Ok(Some(path)),
+            _ => bail!("unsupported URL scheme '{}' in {}", scheme, url),
+        },
+    }
+}
+
+/// Opens a download, which may also be a local path when updating from a mirror directory.
+fn open_download(client: &Client, url: &str) -> Result<(Box<dyn Read>, Option<u64>)> {
+    if let Some(path) = local_path(url)? {
+        let file = fs::File::open(path).with_context(|| format!("cannot open {}", path))?;
+        let len = file.metadata().ok().map(|m| m.len());
+        return Ok((Box::new(file), len));
+    }
+
+    let resp = client
+        .get(url)
+        .header(USER_AGENT, "sortify-updater")
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("cannot download {}", url))?;
+    let len = resp.content_length();
+    Ok((Box::new(resp), len))
+}
+
+fn download_text(client: &Client, url: &str) -> Result<String> {
+    let (mut reader, _) = open_download(client, url)?;
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .with_context(|| format!("cannot read {}", url))?;
+    Ok(text)
+}
+
+fn parse_checksum_line<'a>(line: &'a str, asset_name: &str) -> Option<&'a str> {
+    let mut parts = line.split_whitespace();
+    let hash = parts.next()?;
+    match parts.next() {
+        Some(name) if name.trim_start_matches('*') != asset_name => None,
+        _ => Some(hash),
+    }
+}
+
+/// Looks for `<asset>.sha256` first, then a combined checksum manifest.
+fn expected_checksum(client: &Client, release: &UpdateRelease, asset: &UpdateAsset) -> Result<String> {
+    let sidecar = format!("{}.sha256", asset.name);
+    if let Some(sum) = release.assets.iter().find(|a| a.name == sidecar) {
+        let text = download_text(client, &sum.browser_download_url)?;
+        if let Some(hash) = text.lines().find_map(|l| parse_checksum_line(l, &asset.name)) {
+            return Ok(hash.to_ascii_lowercase());
+        }
+    }
+
+    for manifest in release
+        .assets
+        .iter()
+        .filter(|a| CHECKSUM_MANIFESTS.contains(&a.name.as_str()))
+    {
+        let text = download_text(client, &manifest.browser_download_url)?;
+        let found = text
+            .lines()
+            .filter(|l| l.split_whitespace().count() >= 2)
+            .find_map(|l| parse_checksum_line(l, &asset.name));
+        if let Some(hash) = found {
+            return Ok(hash.to_ascii_lowercase());
+        }
+    }
+
+    bail!("no published SHA-256 checksum found for {}", asset.name)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn download_verified(client: &Client, asset: &UpdateAsset, expected: &str, dest: &Path) -> Result<()> {
+    let (mut resp, len) = open_download(client, &asset.browser_download_url)?;
+
+    let pb = match len {
+        _ if !output::progress_enabled() => ProgressBar::hidden(),
+        Some(len) => ProgressBar::new(len),
+        None => ProgressBar::new_spinner(),
+    };
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.cyan} {msg} {bytes}/{total_bytes}")
+            .unwrap()
+            .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ "),
+    );
+    pb.set_message(format!("Downloading {}", asset.name));
+
+    let mut out = fs::File::create(dest)
+        .with_context(|| format!("cannot create {}", dest.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let n = resp.read(&mut buf).context("download interrupted")?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])
+            .with_context(|| format!("cannot write {}", dest.display()))?;
+        pb.inc(n as u64);
+    }
+    out.sync_all()?;
+    pb.finish_and_clear();
+
+    let actual = to_hex(&hasher.finalize());
+    if actual != expected {
+        bail!(
+            "checksum mismatch for {} (expected {}, got {})",
+            asset.name,
+            expected,
+            actual
+        );
+    }
+
+    Ok(())
+}
+
+fn is_binary_entry(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == BINARY_NAME)
+}
+
+/// Extracts the `sortify` executable from a downloaded release archive.
+fn unpack_binary(archive: &Path, kind: AssetKind, dest: &Path) -> Result<()> {
+    let file = fs::File::open(archive)
+        .with_context(|| format!("cannot open {}", archive.display()))?;
+    let out = || {
+        fs::File::create(dest).with_context(|| format!("cannot create {}", dest.display()))
+    };
+
+    match kind {
+        AssetKind::Binary => bail!("{} is a plain binary, not an archive", archive.display()),
+        AssetKind::TarGz => {
+            let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+            for entry in tar.entries().context("cannot read tar archive")? {
+                let mut entry = entry.context("corrupt tar entry")?;
+                if entry.header().entry_type().is_file() && is_binary_entry(&entry.path()?) {
+                    std::io::copy(&mut entry, &mut out()?).context("cannot unpack binary")?;
+                    return Ok(());
+                }
+            }
+        }
+        AssetKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file).context("cannot read zip archive")?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i).context("corrupt zip entry")?;
+                if entry.is_file() && entry.enclosed_name().is_some_and(|p| is_binary_entry(&p)) {
+                    std::io::copy(&mut entry, &mut out()?).context("cannot unpack binary")?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    bail!("{} does not contain {}", archive.display(), BINARY_NAME)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("cannot set permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn smoke_test(exe: &Path) -> bool {
+    Command::new(exe)
+        .arg("--version")
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let name = exe.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    exe.with_file_name(format!("{}{}", name, suffix))
+}
+
+/// Swaps the running executable for `staged`, keeping `<exe>.bak` and
+/// restoring it if the new binary does not survive `--version`.
+fn replace_executable(exe: &Path, staged: &Path) -> Result<PathBuf> {
+    let backup = sibling(exe, ".bak");
+    if backup.exists() {
+        fs::remove_file(&backup)
+            .with_context(|| format!("cannot remove old backup {}", backup.display()))?;
+    }
+
+    fs::rename(exe, &backup)
+        .with_context(|| format!("cannot back up {} to {}", exe.display(), backup.display()))?;
+
+    if let Err(err) = fs::rename(staged, exe) {
+        fs::rename(&backup, exe).context("cannot restore backup after failed install")?;
+        return Err(err).with_context(|| format!("cannot install new binary to {}", exe.display()));
+    }
+
+    if !smoke_test(exe) {
+        fs::rename(exe, staged).ok();
+        fs::rename(&backup, exe).context("cannot restore backup after failed smoke test")?;
+        fs::remove_file(staged).ok();
+        bail!("new binary failed the --version smoke test; previous version restored");
+    }
+
+    Ok(backup)
+}
+
+pub fn self_update(settings: &UpdateSettings) -> Result<()> {
+    output::info("[ Sortify Updater ]".bright_cyan().bold());
+    output::info("→ Looking for a new version...".dimmed());
+
+    let client = settings.client()?;
+    let releases = fetch_releases(&client, settings).context("cannot fetch release information")?;
+    let current = parse_tag(CURRENT_VERSION).context("invalid current version")?;
+
+    let Some(release) = select_updates(releases, settings.channel).into_iter().next() else {
+        output::info(format!("You're using the latest version (v{})", current).green());
+        return Ok(());
+    };
+    let latest = release.version().context("invalid release tag")?;
+
+    let asset = find_asset(&release, settings.asset_pattern.as_deref()).with_context(|| {
+        format!("no suitable asset found for this platform ({})", Target::current().triple())
+    })?;
+    let expected = expected_checksum(&client, &release, asset)?;
+
+    let exe = std::env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("cannot locate the running executable")?;
+    let staged = sibling(&exe, ".new");
+    let kind = AssetKind::from_name(&asset.name);
+    let download = match kind {
+        AssetKind::Binary => staged.clone(),
+        _ => sibling(&exe, &format!(".new{}", kind.ext())),
+    };
+
+    if let Err(err) = download_verified(&client, asset, &expected, &download) {
+        fs::remove_file(&download).ok();
+        return Err(err);
+    }
+    output::info(format!("{} {}", "Checksum verified:".green(), expected.dimmed()));
+
+    if kind != AssetKind::Binary {
+        let unpacked = unpack_binary(&download, kind, &staged);
+        fs::remove_file(&download).ok();
+        if let Err(err) = unpacked {
+            fs::remove_file(&staged).ok();
+            return Err(err);
+        }
+    }
+    make_executable(&staged)?;
+
+    let backup = replace_executable(&exe, &staged)?;
+
+    output::info(format!("Updated sortify v{} → v{}", current, latest).green().bold());
+    output::info(format!("  Backup kept at: {}", backup.display()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Serves `files` by request path over plain HTTP, standing in for a release host.
+    fn serve(files: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().filter_map(Result::ok) {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).ok();
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                    line.clear();
+                }
+
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let (status, body) = match files.iter().find(|(p, _)| *p == path) {
+                    Some((_, body)) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &b""[..]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).ok();
+                stream.write_all(body).ok();
+            }
+        });
+        base
+    }
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sortify-install-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        to_hex(&Sha256::digest(bytes))
+    }
+
+    fn asset(base: &str, name: &str) -> UpdateAsset {
+        UpdateAsset {
+            name: name.to_string(),
+            browser_download_url: format!("{}/{}", base, name),
+        }
+    }
+
+    fn release(assets: Vec<UpdateAsset>) -> UpdateRelease {
+        UpdateRelease {
+            tag_name: "v99.0.0".to_string(),
+            prerelease: false,
+            draft: false,
+            body: None,
+            assets,
+        }
+    }
+
+    #[test]
+    fn local_paths_and_schemes() {
+        assert_eq!(local_path("https://example.com/a").unwrap(), None);
+        assert_eq!(local_path("HTTP://example.com/a").unwrap(), None);
+        assert_eq!(local_path("file:///srv/mirror/a").unwrap(), Some("/srv/mirror/a"));
+        assert_eq!(local_path("/srv/mirror/a").unwrap(), Some("/srv/mirror/a"));
+        assert!(local_path("htps://example.com/a").is_err());
+        assert!(local_path("ftp://example.com/a").is_err());
+    }
+
+    #[test]
+    fn checksums_come_from_sidecars_then_manifests() {
+        let base = serve(vec![
+            ("/tool.sha256", b"ABCDEF  tool\n".to_vec()),
+            ("/SHA256SUMS", b"111  other\n222 *tool2\n".to_vec()),
+        ]);
+        let rel = release(vec![
+            asset(&base, "tool"),
+            asset(&base, "tool.sha256"),
+            asset(&base, "tool2"),
+            asset(&base, "SHA256SUMS"),
+        ]);
+
+        assert_eq!(expected_checksum(&client(), &rel, &rel.assets[0]).unwrap(), "abcdef");
+        assert_eq!(expected_checksum(&client(), &rel, &rel.assets[2]).unwrap(), "222");
+        let unlisted = asset(&base, "tool3");
+        assert!(expected_checksum(&client(), &rel, &unlisted).is_err());
+    }
+
+    #[test]
+    fn downloads_must_match_their_checksum() {
+        let body = b"new binary".to_vec();
+        let base = serve(vec![("/tool", body.clone())]);
+        let dir = scratch("verify");
+        let dest = dir.join("tool.new");
+
+        download_verified(&client(), &asset(&base, "tool"), &sha256(&body), &dest).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+
+        let err = download_verified(&client(), &asset(&base, "tool"), &sha256(b"other"), &dest).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+        assert!(download_verified(&client(), &asset(&base, "missing"), "", &dest).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn binaries_are_found_inside_archives() {
+        let dir = scratch("unpack");
+        let archive = dir.join("release.tar.gz");
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive).unwrap(),
+            flate2::Compression::fast(),
+        ));
+        for (name, body) in [("release/README.md", &b"readme"[..]), (&format!("release/{}", BINARY_NAME), b"exe")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, name, body).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let dest = dir.join("out");
+        unpack_binary(&archive, AssetKind::TarGz, &dest).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"exe");
+        assert!(unpack_binary(&archive, AssetKind::Binary, &dest).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Downloads `script` as the new binary over HTTP and installs it over an old one.
+    #[cfg(unix)]
+    fn install(name: &str, script: &str) -> (PathBuf, Result<PathBuf>) {
+        let base = serve(vec![("/tool", script.as_bytes().to_vec())]);
+        let dir = scratch(name);
+        let exe = dir.join("sortify");
+        fs::write(&exe, "#!/bin/sh\necho old\n").unwrap();
+        make_executable(&exe).unwrap();
+
+        let staged = sibling(&exe, ".new");
+        download_verified(&client(), &asset(&base, "tool"), &sha256(script.as_bytes()), &staged).unwrap();
+        make_executable(&staged).unwrap();
+        (exe.clone(), replace_executable(&exe, &staged))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn working_binaries_replace_the_old_one() {
+        let (exe, result) = install("replace", "#!/bin/sh\necho new\n");
+        let backup = result.unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "#!/bin/sh\necho new\n");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "#!/bin/sh\necho old\n");
+        assert!(!sibling(&exe, ".new").exists());
+        fs::remove_dir_all(exe.parent().unwrap()).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_binaries_are_rolled_back() {
+        let (exe, result) = install("rollback", "#!/bin/sh\nexit 1\n");
+        assert!(result.unwrap_err().to_string().contains("previous version restored"));
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "#!/bin/sh\necho old\n");
+        assert!(!sibling(&exe, ".new").exists());
+        assert!(!sibling(&exe, ".bak").exists());
+        fs::remove_dir_all(exe.parent().unwrap()).ok();
+    }
+}
@@ -1,4 +1,9 @@
+mod cache;
 mod github;
+mod install;
 mod platform_check;
+mod settings;
 
-pub use github::check_for_updates;
\ No newline at end of file
+pub use github::check_for_updates;
+pub use install::self_update;
+pub use settings::{Channel, UpdateSettings};
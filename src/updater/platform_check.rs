@@ -1,9 +1,224 @@
 pub fn target_suffix() -> &'static str {
     match () {
         _ if cfg!(all(target_os = "windows", target_arch = "x86_64")) => "windows-x86_64.exe",
+        _ if cfg!(all(target_os = "windows", target_arch = "aarch64")) => "windows-arm64.exe",
+        _ if cfg!(all(target_os = "linux", target_arch = "x86_64", target_env = "musl")) => "linux-x86_64-musl",
         _ if cfg!(all(target_os = "linux", target_arch = "x86_64")) => "linux-x86_64",
+        _ if cfg!(all(target_os = "linux", target_arch = "aarch64", target_env = "musl")) => "linux-aarch64-musl",
+        _ if cfg!(all(target_os = "linux", target_arch = "aarch64")) => "linux-aarch64",
         _ if cfg!(all(target_os = "macos", target_arch = "x86_64")) => "macos-x86_64",
         _ if cfg!(all(target_os = "macos", target_arch = "aarch64")) => "macos-arm64",
+        _ if cfg!(all(target_os = "freebsd", target_arch = "x86_64")) => "freebsd-x86_64",
         _ => "unknown",
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Binary,
+    TarGz,
+    Zip,
+}
+
+impl AssetKind {
+    pub fn from_name(name: &str) -> Self {
+        let lower = name.to_ascii_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            AssetKind::TarGz
+        } else if lower.ends_with(".zip") {
+            AssetKind::Zip
+        } else {
+            AssetKind::Binary
+        }
+    }
+
+    pub fn ext(self) -> &'static str {
+        match self {
+            AssetKind::Binary if cfg!(windows) => ".exe",
+            AssetKind::Binary => "",
+            AssetKind::TarGz => ".tar.gz",
+            AssetKind::Zip => ".zip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Target {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub libc: Option<&'static str>,
+}
+
+impl Target {
+    pub fn current() -> Self {
+        let libc = match () {
+            _ if !cfg!(target_os = "linux") => None,
+            _ if cfg!(target_env = "musl") => Some("musl"),
+            _ => Some("gnu"),
+        };
+        Self {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            libc,
+        }
+    }
+
+    /// Rust-style target triple, e.g. `aarch64-unknown-linux-musl`.
+    pub fn triple(&self) -> String {
+        match self.os {
+            "linux" => format!("{}-unknown-linux-{}", self.arch, self.libc.unwrap_or("gnu")),
+            "macos" => format!("{}-apple-darwin", self.arch),
+            "windows" => format!("{}-pc-windows-msvc", self.arch),
+            os => format!("{}-unknown-{}", self.arch, os),
+        }
+    }
+
+    fn os_aliases(&self) -> &'static [&'static str] {
+        match self.os {
+            "linux" => &["linux"],
+            "macos" => &["macos", "darwin", "apple", "osx"],
+            "windows" => &["windows", "win64", "win"],
+            "freebsd" => &["freebsd"],
+            _ => &[],
+        }
+    }
+
+    fn arch_aliases(&self) -> &'static [&'static str] {
+        match self.arch {
+            "x86_64" => &["x86_64", "x86-64", "amd64", "x64"],
+            "aarch64" => &["aarch64", "arm64"],
+            "x86" => &["i686", "i386", "x86"],
+            "arm" => &["armv7", "armhf", "arm"],
+            _ => &[],
+        }
+    }
+
+    /// Scores an asset name against this target; `None` means it cannot run here.
+    pub fn score(&self, name: &str) -> Option<u32> {
+        let lower = name.to_ascii_lowercase();
+        if is_auxiliary(&lower) {
+            return None;
+        }
+
+        let has = |aliases: &[&str]| aliases.iter().any(|a| contains_token(&lower, a));
+        // `x86` is also the start of `x86_64`, so 64-bit names must not pass for 32-bit ones
+        let has_arch = match self.arch {
+            "x86" => {
+                let narrow = lower.replace("x86_64", "").replace("x86-64", "");
+                self.arch_aliases().iter().any(|a| contains_token(&narrow, a))
+            }
+            _ => has(self.arch_aliases()),
+        };
+        if !has(self.os_aliases()) || !has_arch {
+            return None;
+        }
+
+        let is_musl = contains_token(&lower, "musl");
+        let mut score = 10;
+        match self.libc {
+            Some("musl") if !is_musl => return None,
+            Some("musl") => score += 5,
+            // Static musl builds run on glibc systems too, just rank them lower.
+            Some(_) if is_musl => score -= 2,
+            _ => {}
+        }
+        if lower.contains(&self.triple()) {
+            score += 5;
+        }
+        if lower.ends_with(target_suffix()) {
+            score += 3;
+        }
+        Some(score)
+    }
+}
+
+fn is_auxiliary(name: &str) -> bool {
+    [".sha256", ".sha512", ".sig", ".asc", ".minisig", ".txt", ".json", ".pem"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+fn contains_token(haystack: &str, token: &str) -> bool {
+    haystack.match_indices(token).any(|(i, _)| {
+        let before = haystack[..i].chars().next_back();
+        let after = haystack[i + token.len()..].chars().next();
+        let boundary = |c: Option<char>| c.is_none_or(|c| !c.is_ascii_alphanumeric());
+        boundary(before) && boundary(after)
+    })
+}
+
+/// Expands `{tag}`, `{version}`, `{target}`, `{os}`, `{arch}`, `{libc}` and `{ext}` in an asset-name pattern.
+pub fn expand_asset_pattern(pattern: &str, tag: &str, target: &Target, kind: AssetKind) -> String {
+    let version = tag.trim_start_matches(|c: char| !c.is_ascii_digit());
+    pattern
+        .replace("{tag}", tag)
+        .replace("{version}", version)
+        .replace("{target}", &target.triple())
+        .replace("{os}", target.os)
+        .replace("{arch}", target.arch)
+        .replace("{libc}", target.libc.unwrap_or(""))
+        .replace("{ext}", kind.ext())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX_GNU: Target = Target { os: "linux", arch: "x86_64", libc: Some("gnu") };
+    const LINUX_MUSL: Target = Target { os: "linux", arch: "x86_64", libc: Some("musl") };
+    const LINUX_X86: Target = Target { os: "linux", arch: "x86", libc: Some("gnu") };
+    const MACOS_ARM: Target = Target { os: "macos", arch: "aarch64", libc: None };
+
+    #[test]
+    fn tokens_need_boundaries() {
+        assert!(contains_token("sortify-linux-x86_64.tar.gz", "linux"));
+        assert!(contains_token("sortify_linux_amd64", "amd64"));
+        assert!(!contains_token("sortify-darwin-arm64", "arm"));
+        assert!(!contains_token("sortify-winx64", "win"));
+    }
+
+    #[test]
+    fn x86_does_not_match_x86_64() {
+        assert_eq!(LINUX_X86.score("sortify-linux-x86_64.tar.gz"), None);
+        assert_eq!(LINUX_X86.score("sortify-linux-x86-64"), None);
+        assert!(LINUX_X86.score("sortify-linux-x86.tar.gz").is_some());
+        assert!(LINUX_X86.score("sortify-i686-unknown-linux-gnu").is_some());
+        assert!(LINUX_GNU.score("sortify-linux-x86-64").is_some());
+        assert_eq!(LINUX_GNU.score("sortify-linux-x86.tar.gz"), None);
+    }
+
+    #[test]
+    fn scores_prefer_the_exact_target() {
+        let triple = LINUX_GNU.score("sortify-x86_64-unknown-linux-gnu.tar.gz").unwrap();
+        let loose = LINUX_GNU.score("sortify-linux-amd64.tar.gz").unwrap();
+        let musl = LINUX_GNU.score("sortify-x86_64-unknown-linux-musl.tar.gz").unwrap();
+        assert!(triple > loose);
+        assert!(loose > musl);
+
+        assert_eq!(LINUX_MUSL.score("sortify-linux-amd64.tar.gz"), None);
+        assert!(LINUX_MUSL.score("sortify-linux-amd64-musl.tar.gz").is_some());
+        assert!(MACOS_ARM.score("sortify-darwin-arm64.zip").is_some());
+        assert_eq!(MACOS_ARM.score("sortify-linux-arm64.zip"), None);
+    }
+
+    #[test]
+    fn checksums_and_signatures_are_never_picked() {
+        assert_eq!(LINUX_GNU.score("sortify-linux-x86_64.tar.gz.sha256"), None);
+        assert_eq!(LINUX_GNU.score("sortify-linux-x86_64.tar.gz.asc"), None);
+    }
+
+    #[test]
+    fn patterns_expand_every_placeholder() {
+        let name = expand_asset_pattern("sortify-{version}-{target}{ext}", "v1.2.0", &MACOS_ARM, AssetKind::TarGz);
+        assert_eq!(name, "sortify-1.2.0-aarch64-apple-darwin.tar.gz");
+        let name = expand_asset_pattern("{tag}_{os}_{arch}_{libc}", "v2", &LINUX_MUSL, AssetKind::Zip);
+        assert_eq!(name, "v2_linux_x86_64_musl");
+    }
+
+    #[test]
+    fn asset_kinds_come_from_the_extension() {
+        assert_eq!(AssetKind::from_name("sortify.TGZ"), AssetKind::TarGz);
+        assert_eq!(AssetKind::from_name("sortify-win.zip"), AssetKind::Zip);
+        assert_eq!(AssetKind::from_name("sortify-linux"), AssetKind::Binary);
+    }
+}
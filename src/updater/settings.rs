@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use reqwest::blocking::Client;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::filter::parse_duration;
+use crate::paths::env_flag;
+
+const DEFAULT_API_BASE: &str = "https://api.github.com/repos/OctoBanon-Main/sortify";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Final releases only
+    #[default]
+    Stable,
+    /// Final releases plus alpha, beta and rc builds
+    Beta,
+    /// Every published release, including nightly and dev builds
+    Nightly,
+}
+
+impl Channel {
+    pub fn allows(self, version: &Version, marked_prerelease: bool) -> bool {
+        let is_pre = marked_prerelease || !version.pre.is_empty();
+        match self {
+            Channel::Stable => !is_pre,
+            Channel::Beta => {
+                let tag = version
+                    .pre
+                    .as_str()
+                    .split('.')
+                    .next()
+                    .unwrap_or("")
+                    .trim_end_matches(|c: char| c.is_ascii_digit())
+                    .to_ascii_lowercase();
+                matches!(tag.as_str(), "" | "alpha" | "beta" | "rc" | "pre" | "preview")
+            }
+            Channel::Nightly => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UpdateSource {
+    /// GitHub-compatible release API base (`<base>/releases`, `<base>/releases/latest`)
+    Api(String),
+    /// JSON file holding one release object or a list of them
+    File(PathBuf),
+}
+
+impl UpdateSource {
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix("file://") {
+            UpdateSource::File(PathBuf::from(path))
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            UpdateSource::Api(s.trim_end_matches('/').to_string())
+        } else {
+            UpdateSource::File(PathBuf::from(s))
+        }
+    }
+
+    pub fn cache_key(&self) -> String {
+        match self {
+            UpdateSource::Api(url) => url.clone(),
+            UpdateSource::File(path) => format!("file://{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateSettings {
+    pub enabled: bool,
+    pub channel: Channel,
+    pub source: UpdateSource,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub asset_pattern: Option<String>,
+}
+
+impl UpdateSettings {
+    /// Command-line flags win over environment variables, which win over the config file.
+    pub fn resolve(args: &Args, config: &Config) -> Result<Self> {
+        let cfg = &config.updates;
+
+        let source = args
+            .update_source
+            .clone()
+            .or_else(|| std::env::var("SORTIFY_UPDATE_SOURCE").ok().filter(|s| !s.is_empty()))
+            .or_else(|| cfg.source.clone())
+            .map(|s| UpdateSource::parse(&s))
+            .unwrap_or_else(|| UpdateSource::Api(DEFAULT_API_BASE.to_string()));
+
+        let interval = match args.update_interval {
+            Some(d) => d,
+            None => parse_duration(&cfg.interval)
+                .map_err(anyhow::Error::msg)
+                .context("invalid updates.interval in config")?,
+        };
+
+        Ok(Self {
+            enabled: !args.no_check_updates && !env_flag("SORTIFY_NO_UPDATE_CHECK") && cfg.check,
+            channel: args
+                .channel
+                .or(args.prerelease_channel.then_some(Channel::Beta))
+                .or(cfg.channel)
+                .unwrap_or(if cfg.prerelease { Channel::Beta } else { Channel::Stable }),
+            source,
+            interval,
+            timeout: Duration::from_secs(cfg.timeout_secs.max(1)),
+            asset_pattern: std::env::var("SORTIFY_ASSET_PATTERN")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .or_else(|| cfg.asset_pattern.clone()),
+        })
+    }
+
+    /// HTTP(S)_PROXY and NO_PROXY are honoured by reqwest's default proxy setup.
+    pub fn client(&self) -> Result<Client> {
+        Client::builder()
+            .connect_timeout(self.timeout)
+            .timeout(self.timeout)
+            .build()
+            .context("cannot build HTTP client")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn allows(channel: Channel, version: &str, marked: bool) -> bool {
+        channel.allows(&Version::parse(version).unwrap(), marked)
+    }
+
+    #[test]
+    fn stable_takes_final_releases_only() {
+        assert!(allows(Channel::Stable, "1.0.0", false));
+        assert!(!allows(Channel::Stable, "1.0.0", true));
+        assert!(!allows(Channel::Stable, "1.0.0-rc.1", false));
+    }
+
+    #[test]
+    fn beta_takes_test_builds_but_not_nightlies() {
+        assert!(allows(Channel::Beta, "1.0.0", false));
+        assert!(allows(Channel::Beta, "1.0.0", true));
+        assert!(allows(Channel::Beta, "1.0.0-alpha", false));
+        assert!(allows(Channel::Beta, "1.0.0-Beta2", false));
+        assert!(allows(Channel::Beta, "1.0.0-rc.3", false));
+        assert!(!allows(Channel::Beta, "1.0.0-nightly.20240101", false));
+        assert!(!allows(Channel::Beta, "1.0.0-dev", true));
+    }
+
+    #[test]
+    fn nightly_takes_everything() {
+        assert!(allows(Channel::Nightly, "1.0.0-nightly.20240101", true));
+        assert!(allows(Channel::Nightly, "1.0.0", false));
+    }
+
+    #[test]
+    fn sources_are_urls_or_files() {
+        let api = |s| match UpdateSource::parse(s) {
+            UpdateSource::Api(url) => Some(url),
+            UpdateSource::File(_) => None,
+        };
+        let file = |s| match UpdateSource::parse(s) {
+            UpdateSource::File(path) => Some(path),
+            UpdateSource::Api(_) => None,
+        };
+        assert_eq!(api("https://example.com/api/").as_deref(), Some("https://example.com/api"));
+        assert_eq!(file("file:///srv/releases.json").as_deref(), Some(Path::new("/srv/releases.json")));
+        assert_eq!(file(" releases.json ").as_deref(), Some(Path::new("releases.json")));
+    }
+}